@@ -1,22 +1,100 @@
-use chrono::{Datelike, Days, Months, NaiveDate};
-use color_eyre::{Result};
-use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use ::directories::ProjectDirs;
+use chrono::{DateTime, Datelike, Days, Local, Locale, Months, NaiveDate, TimeDelta, Utc};
+use color_eyre::Result;
+use crossterm::event::{
+    self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers, MouseButton, MouseEvent,
+    MouseEventKind,
+};
+use google_calendar3::api::{
+    CalendarListEntry, Event as CalendarEvent, EventAttendee, EventDateTime,
+};
 use ratatui::{
-    layout::{Constraint::{self}, Direction, Layout, Rect}, text::Text, DefaultTerminal, Frame
+    layout::{
+        Constraint::{self},
+        Direction, Flex, Layout, Offset, Position, Rect,
+    },
+    style::Modifier,
+    text::{Line, Text},
+    widgets::Clear,
+    DefaultTerminal, Frame,
 };
-use google_calendar3::api::{CalendarListEntry, Event as CalendarEvent};
-use std::path::PathBuf;
-use::directories::ProjectDirs;
 use std::fs;
+use std::{
+    collections::{HashMap, VecDeque},
+    path::PathBuf,
+    time::{Duration, Instant},
+};
+use tokio::sync::mpsc::{self, UnboundedReceiver, UnboundedSender};
+use tokio::task::JoinHandle;
+
+use crate::colors::{event_color, ColorSupport, EventColor};
+use crate::compact_layout::{busy_days, AgendaWidget, CompactMonthWidget, COMPACT_DAY_WIDTH};
+use crate::config::Config;
+use crate::edit_event_form::{draw_edit_event_form, EditEventForm};
+use crate::error::TalendarError;
+use crate::error_log_popup::{draw_error_log_popup, ErrorLogEntry};
+use crate::find_time::{
+    draw_find_time_form, draw_find_time_results, find_free_slots, BusySource, FindTimeForm,
+    FindTimeRequest, FindTimeResults, FreeSlot,
+};
+use crate::form::FormEvent;
+use crate::google_cal_backend::{CalendarEventExt, ResponseStatus, SyncProgress, SyncedData};
+use crate::history::{AppliedChange, History};
+use crate::quick_add::{draw_quick_add_preview, parse_quick_add, QuickAddEvent, QuickAddParser};
+use crate::recurrence::{draw_scope_chooser, RecurrenceScope, ScopeChooser};
+use crate::reminders::{due_reminders, Reminder};
+use crate::status_bar::StatusBar;
+use crate::text_prompt::{draw_text_prompt, PromptEvent, TextPrompt};
+use crate::theme::Theme;
+use crate::utils::{
+    date_string, is_weekend, iso_week_number, month_year_string, ordered_weekdays, start_of_week,
+    weekday_short_name, weeks_in_month,
+};
+use crate::{
+    calendar_day_widget::{event_rows, CalendarDayWidget, CalendarDayWidgetState},
+    event_details_popup::draw_event_details_popup,
+    event_widget::EventWidget,
+    google_cal_backend::CalendarClient,
+    inspect_day_popup::draw_inspect_day_popup,
+};
+
+/// width of the gutter that the week numbers are drawn in
+const WEEK_NUMBER_GUTTER_WIDTH: u16 = 3;
+
+/// how long the main loop waits for an event before doing its periodic work
+const TICK_RATE: Duration = Duration::from_millis(250);
+
+/// the smallest terminal that the month grid is readable in
+const MONTH_VIEW_MIN_SIZE: (u16, u16) = (84, 24);
+
+/// the smallest terminal that the compact month fits in with the agenda below it, and beside it
+const COMPACT_STACKED_MIN_SIZE: (u16, u16) = (30, 18);
+const COMPACT_SIDE_BY_SIDE_MIN_SIZE: (u16, u16) = (60, 10);
+
+/// below this, not even the agenda fits
+const MIN_SIZE: (u16, u16) = (20, 6);
+
+/// how often we try to reach Google again while we're offline
+const OFFLINE_SYNC_INTERVAL: Duration = Duration::from_secs(30);
+
+/// starts the ids of events created while offline, which are shown until Google has them. Google's
+/// own ids never have a dash.
+const UNSENT_EVENT_ID_PREFIX: &str = "unsent-";
 
-use crate::{calendar_day_widget::{CalendarDayWidget, CalendarDayWidgetState}, event_widget::EventWidget, google_cal_backend::CalendarClient, inspect_day_popup::draw_inspect_day_popup};
-use crate::utils::month_to_str;
+/// two clicks on the same cell within this long are a double click
+const DOUBLE_CLICK_INTERVAL: Duration = Duration::from_millis(500);
 
 pub struct App {
     /// Is the application running?
     running: bool,
     #[allow(dead_code)]
     view: CalendarView,
+    config: Config,
+    /// locale used to format dates, resolved from the config
+    locale: Locale,
+    /// how many colors the terminal can show, resolved from the config
+    color_support: ColorSupport,
+    theme: Theme,
     calendar_client: CalendarClient,
     /// calendars (ids) to display to the user
     #[allow(dead_code)]
@@ -26,44 +104,238 @@ pub struct App {
     currently_selected_date: NaiveDate,
     // [selected day, other days]
     calendar_view_state: (CalendarDayWidgetState, CalendarDayWidgetState),
+    /// are the keys moving between days or between the events of the selected day?
+    focus: Focus,
+
+    /// hide events whose invitation we've declined
+    hide_declined_events: bool,
 
     show_inspect_day_popup: bool,
+    show_event_details_popup: bool,
+    /// number of lines the event details popup is scrolled down by
+    event_details_scroll: u16,
+    /// a prompt for text input, and what the input is for
+    prompt: Option<(TextPrompt, PromptPurpose)>,
+    /// a one-off message for the user, shown at the bottom of the screen until the next key press
+    message: Option<String>,
+    /// the latest error, shown at the bottom of the screen until the next key press
+    error: Option<String>,
+    /// every error since talendar started, oldest first
+    error_log: Vec<ErrorLogEntry>,
+    show_error_log: bool,
+    /// number of lines the error log popup is scrolled down by
+    error_log_scroll: u16,
+    /// actions that need the calendar api, oldest first, to be performed by the main loop one at
+    /// a time
+    pending_actions: VecDeque<Action>,
+    /// the sync running in the background, if there is one
+    sync_task: Option<JoinHandle<Result<SyncedData, TalendarError>>>,
+    /// when the last sync started
+    sync_started: Instant,
+    /// how far through its calendars the running sync is, once it's found out how many there are
+    sync_progress: Option<SyncProgress>,
+    sync_progress_sender: UnboundedSender<SyncProgress>,
+    sync_progress_receiver: UnboundedReceiver<SyncProgress>,
+    /// whether Google couldn't be reached the last time we tried
+    offline: bool,
+    /// changes made while offline, oldest first, to be sent once Google can be reached
+    offline_edits: VecDeque<OfflineEdit>,
+    /// whether the user was told that quitting would lose the changes made while offline, so
+    /// that quitting straight away again goes ahead
+    quit_warned: bool,
+    /// the changes made since talendar started, to undo and redo
+    history: History,
+    /// the name of the local time zone, e.g. "Australia/Melbourne"
+    timezone: String,
+    /// reminders that have fired and are waiting to be snoozed or dismissed, oldest first
+    reminders: Vec<Reminder>,
+    /// keys of snoozed reminders, and when they should show again
+    snoozed_reminders: HashMap<String, DateTime<Local>>,
+    find_time_form: Option<FindTimeForm>,
+    /// the form editing an event, and the event as it was before
+    edit_event_form: Option<(EditEventForm, Box<CalendarEvent>)>,
+    find_time_results: Option<FindTimeResults>,
+    /// quick add text and what we understood from it, waiting for the user to confirm
+    quick_add_preview: Option<(String, QuickAddEvent)>,
+    /// asks which events of a series a change applies to, and the change
+    scope_chooser: Option<(ScopeChooser, EventChange)>,
+    /// where things were drawn in the last frame, so that mouse clicks can be matched to them
+    click_areas: ClickAreas,
+    /// when and where the last left click was, to detect double clicks
+    last_click: Option<(Instant, u16, u16)>,
+}
+
+/// The parts of the screen that respond to mouse clicks
+#[derive(Default)]
+struct ClickAreas {
+    previous_month: Rect,
+    next_month: Rect,
+    days: Vec<(NaiveDate, Rect)>,
+    /// the list of the selected day's events in the compact layouts
+    agenda: Option<Rect>,
+    /// the area of the inspect day popup and the index of the first event shown in it
+    inspect_day_popup: Option<(Rect, usize)>,
+}
+
+/// What the input of the open [`TextPrompt`] will be used for
+enum PromptPurpose {
+    /// a comment to send with our response to the selected event
+    ResponseComment(ResponseStatus),
+    /// the title of a new event in a free slot, with the people we looked for the slot with
+    NewEventTitle {
+        slot: FreeSlot,
+        attendees: Vec<String>,
+    },
+    /// a line of text to create an event from
+    QuickAdd,
+}
+
+/// A change to an existing event, which may apply to more of its series
+#[derive(Clone)]
+enum EventChange {
+    Delete(Box<CalendarEvent>),
+    Update {
+        event: Box<CalendarEvent>,
+        changes: Box<CalendarEvent>,
+    },
+}
+
+/// Actions that need to wait on the calendar api. Key handlers can't await, so they leave these
+/// for the main loop to perform.
+#[derive(Clone)]
+enum Action {
+    RespondToEvent {
+        event: Box<CalendarEvent>,
+        response: ResponseStatus,
+        comment: Option<String>,
+    },
+    FindTime(FindTimeRequest),
+    QuickAdd {
+        text: String,
+        event: Box<QuickAddEvent>,
+    },
+    CreateEvent {
+        calendar_id: String,
+        event: Box<CalendarEvent>,
+    },
+    ChangeEvent {
+        change: EventChange,
+        scope: RecurrenceScope,
+    },
+    /// revert the last change in the [`History`]
+    Undo,
+    /// make the last undone change again
+    Redo,
+}
+
+/// A change that couldn't be sent because Google couldn't be reached. It's shown in the cache
+/// meanwhile, as far as we can tell what Google will make of it.
+struct OfflineEdit {
+    action: Action,
+    /// the id of the event shown in place of the one the change creates
+    unsent_event_id: Option<String>,
+}
+
+#[derive(Debug, Default, PartialEq, Eq)]
+enum Focus {
+    #[default]
+    Days,
+    Events,
+}
+
+/// How the calendar is arranged, which depends on the size of the terminal
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum ScreenLayout {
+    /// a grid of days with their events
+    Month,
+    /// a small month next to or above the events of the selected day
+    CompactMonth { side_by_side: bool },
+    /// only the events of the selected day
+    Agenda,
+}
+
+impl ScreenLayout {
+    /// Returns the layout that best fits `area`, or `None` if it's too small for any of them
+    fn for_area(area: Rect) -> Option<Self> {
+        let fits = |(width, height): (u16, u16)| area.width >= width && area.height >= height;
+        if fits(MONTH_VIEW_MIN_SIZE) {
+            Some(ScreenLayout::Month)
+        } else if fits(COMPACT_SIDE_BY_SIDE_MIN_SIZE) {
+            Some(ScreenLayout::CompactMonth { side_by_side: true })
+        } else if fits(COMPACT_STACKED_MIN_SIZE) {
+            Some(ScreenLayout::CompactMonth {
+                side_by_side: false,
+            })
+        } else if fits(MIN_SIZE) {
+            Some(ScreenLayout::Agenda)
+        } else {
+            None
+        }
+    }
+
+    /// The name shown in the status bar
+    fn name(self) -> &'static str {
+        match self {
+            ScreenLayout::Month => "Month",
+            ScreenLayout::CompactMonth { .. } => "Compact month",
+            ScreenLayout::Agenda => "Agenda",
+        }
+    }
 }
 
 #[derive(Debug, Default)]
 enum CalendarView {
     #[default]
-    MONTH,
-    _WEEK,
-    _DAY,
+    Month,
+    _Week,
+    _Day,
 }
 
+pub fn determine_cache_path() -> Result<PathBuf, TalendarError> {
+    let Some(project_dir) = ProjectDirs::from("", "", "talendar") else {
+        return Err(TalendarError::Config(String::from(
+            "Couldn't find a home directory to keep the cache in",
+        )));
+    };
+    let data_dir = project_dir.data_dir();
 
-fn determine_cache_path() -> PathBuf {
-    if let Some(project_dir) = ProjectDirs::from("", "", "talendar") {
-        let data_dir = project_dir.data_dir();
+    fs::create_dir_all(data_dir).map_err(|source| TalendarError::Cache {
+        path: data_dir.to_path_buf(),
+        source,
+    })?;
 
-        fs::create_dir_all(data_dir).expect("Failed to create data directory");
+    let mut path = data_dir.to_path_buf();
+    path.push("cache.json");
+    Ok(path)
+}
 
-        let mut path = data_dir.to_path_buf();
-        path.push("cache.json");
-        path
+/// The file the login token is kept in, next to the cache
+pub fn determine_token_path() -> Result<PathBuf, TalendarError> {
+    let cache_path = determine_cache_path()?;
+    let token_path = cache_path.with_file_name("token.json");
 
-    } else {
-        panic!("Failed to crete data directory");
+    // the token used to be kept at the cache's path with "tokenfile.json" stuck on the end
+    let legacy_token_path = PathBuf::from(
+        cache_path
+            .into_os_string()
+            .into_string()
+            .unwrap_or_default()
+            + "tokenfile.json",
+    );
+    if legacy_token_path.exists() && !token_path.exists() {
+        let _ = fs::rename(&legacy_token_path, &token_path);
     }
+    Ok(token_path)
 }
 
 impl App {
-    /// Construct a new instance of [`App`].
-    pub async fn new() -> Self {
-        // data directory for us to persist out calendar data
-        let cache_path = determine_cache_path();
-
-        // connect to google calendar api by creating a new CalendarClient
-        let calendar_client = CalendarClient::new(cache_path).await.unwrap();
+    /// Construct a new instance of [`App`] showing the calendars of `calendar_client`. The cached
+    /// calendars are shown while they sync in the background.
+    pub fn new(calendar_client: CalendarClient, config: Config) -> Self {
+        let (sync_progress_sender, sync_progress_receiver) = mpsc::unbounded_channel();
+        let sync_task =
+            calendar_client.start_sync(config.sync_concurrency, sync_progress_sender.clone());
 
-        
         // enable all calendars by default
         // note that we're cloning the list of calendars, which means we need to remember to update
         // the ui if we add/remove calendars
@@ -74,105 +346,796 @@ impl App {
             .map(|cal| (cal, true))
             .collect();
 
-        let mut new_app = Self { 
-            running: true, 
-            view: CalendarView::MONTH, 
+        let locale = config.locale();
+        let color_support = config.color_support.detect();
+        let theme = Theme::load(config.theme);
+        let hide_declined_events = config.hide_declined_events;
+        let timezone = localzone::get_local_zone()
+            .unwrap_or_else(|| Local::now().format("UTC%:z").to_string());
+
+        Self {
+            running: true,
+            view: CalendarView::Month,
+            config,
+            locale,
+            color_support,
+            theme,
             calendar_client,
             active_calendars,
             currently_selected_date: chrono::offset::Local::now().date_naive(),
-            calendar_view_state: (CalendarDayWidgetState::default(), CalendarDayWidgetState::default()),
-            show_inspect_day_popup: false
-        };
+            calendar_view_state: (
+                CalendarDayWidgetState::default(),
+                CalendarDayWidgetState::default(),
+            ),
+            focus: Focus::Days,
+            hide_declined_events,
+            show_inspect_day_popup: false,
+            show_event_details_popup: false,
+            event_details_scroll: 0,
+            prompt: None,
+            message: None,
+            error: None,
+            error_log: Vec::new(),
+            show_error_log: false,
+            error_log_scroll: 0,
+            pending_actions: VecDeque::new(),
+            sync_task: Some(sync_task),
+            sync_started: Instant::now(),
+            sync_progress: None,
+            sync_progress_sender,
+            sync_progress_receiver,
+            offline: false,
+            offline_edits: VecDeque::new(),
+            quit_warned: false,
+            history: History::default(),
+            timezone,
+            reminders: Vec::new(),
+            snoozed_reminders: HashMap::new(),
+            find_time_form: None,
+            edit_event_form: None,
+            find_time_results: None,
+            quick_add_preview: None,
+            scope_chooser: None,
+            click_areas: ClickAreas::default(),
+            last_click: None,
+        }
+    }
+
+    /// Tells the user about an error that talendar can carry on after, and keeps it in the error
+    /// log
+    pub fn report_error(&mut self, context: &str, err: TalendarError) {
+        let message = self.log_error(context, err);
+        self.error = Some(message);
+        self.message = None;
+    }
 
-        let _ = new_app.calendar_client.sync().await;
+    /// Shows the cache as it is, because Google couldn't be reached to log in. Syncs are tried
+    /// every so often until it can be.
+    pub fn start_offline(&mut self) {
+        self.offline = true;
+    }
 
-        new_app
+    /// Keeps an error in the error log without showing it, and returns its message
+    fn log_error(&mut self, context: &str, err: TalendarError) -> String {
+        let message = format!("{context}: {err}");
+        self.error_log.push(ErrorLogEntry {
+            time: Local::now(),
+            message: message.clone(),
+        });
+        message
     }
 
     /// Run the application's main loop.
-    pub fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
+    pub async fn run(mut self, mut terminal: DefaultTerminal) -> Result<()> {
         self.running = true;
         while self.running {
             terminal.draw(|frame| self.draw(frame))?;
             self.handle_crossterm_events()?;
+            self.update_reminders();
+
+            while let Ok(progress) = self.sync_progress_receiver.try_recv() {
+                self.sync_progress = Some(progress);
+            }
+            if self
+                .sync_task
+                .as_ref()
+                .is_some_and(|task| task.is_finished())
+            {
+                self.finish_sync().await;
+            }
+            if self.offline
+                && self.sync_task.is_none()
+                && self.sync_started.elapsed() >= OFFLINE_SYNC_INTERVAL
+            {
+                self.start_sync();
+            }
+
+            // a sync that's running could overwrite the changes an action makes, so actions wait
+            // for it to finish
+            if self.sync_task.is_some() {
+                continue;
+            }
+            if let Some(action) = self.pending_actions.pop_front() {
+                self.perform_action(action).await;
+            } else if !self.offline {
+                if let Some(edit) = self.offline_edits.pop_front() {
+                    let waiting = self.offline_edits.len();
+                    // Google's version of the change takes the place of ours if it's made, and
+                    // ours is shown again if it can't be
+                    self.hide_offline_edit(&edit);
+                    self.perform_action(edit.action).await;
+                    // if it failed again it went to the back, but it should still be made first
+                    if self.offline_edits.len() > waiting {
+                        self.offline_edits.rotate_right(1);
+                    }
+                }
+            }
         }
         Ok(())
     }
 
+    /// Starts syncing in the background, unless a sync is running already
+    fn start_sync(&mut self) {
+        if self.sync_task.is_none() {
+            // throw away any progress the last sync sent after we last looked
+            while self.sync_progress_receiver.try_recv().is_ok() {}
+            let progress = self.sync_progress_sender.clone();
+            self.sync_task = Some(
+                self.calendar_client
+                    .start_sync(self.config.sync_concurrency, progress),
+            );
+            self.sync_started = Instant::now();
+            self.sync_progress = None;
+        }
+    }
+
+    /// Merges the result of the finished sync into the cache, or tells the user why it failed
+    async fn finish_sync(&mut self) {
+        let Some(task) = self.sync_task.take() else {
+            return;
+        };
+        let result = match task.await {
+            Ok(result) => result,
+            // the sync panicked, which would have crashed talendar if it hadn't been in a task
+            Err(err) => std::panic::resume_unwind(err.into_panic()),
+        };
+
+        match result.and_then(|data| self.calendar_client.apply_sync(data)) {
+            Ok(failed) => {
+                self.offline = false;
+                self.update_active_calendars();
+                self.clamp_event_selection();
+                for calendar in failed {
+                    self.report_error(&format!("Couldn't sync {}", calendar.name), calendar.error);
+                }
+            }
+            // the status bar says we're offline, so only the first failure needs logging
+            Err(err @ TalendarError::Network(_)) => {
+                if !self.offline {
+                    self.log_error("Couldn't sync", err);
+                }
+                self.offline = true;
+            }
+            Err(err) => self.report_error("Couldn't sync, so the calendar may be out of date", err),
+        }
+    }
+
+    /// Adds calendars that the sync found to the active calendars, and removes ones that have
+    /// gone. Calendars that were there already stay on or off.
+    fn update_active_calendars(&mut self) {
+        self.active_calendars = self
+            .calendar_client
+            .get_calendars()
+            .iter()
+            .map(|calendar| {
+                let active = self
+                    .active_calendars
+                    .iter()
+                    .find(|(active_calendar, _)| active_calendar.id == calendar.id)
+                    .is_none_or(|(_, active)| *active);
+                (calendar.clone(), active)
+            })
+            .collect();
+    }
+
+    /// Tells the user that a change couldn't be made. If Google couldn't be reached the change
+    /// is kept, to be made once it can be.
+    fn edit_failed(&mut self, context: &str, edit: Action, err: TalendarError) {
+        let TalendarError::Network(_) = err else {
+            return self.report_error(context, err);
+        };
+        if !self.offline {
+            self.log_error(context, err);
+        }
+        let unsent_event_id = self.show_offline_edit(&edit);
+        self.offline_edits.push_back(OfflineEdit {
+            action: edit,
+            unsent_event_id,
+        });
+        self.offline = true;
+        self.sync_started = Instant::now();
+        self.message = Some(String::from(
+            "Offline, so the change will be sent once Google can be reached",
+        ));
+    }
+
+    /// Shows a change made while offline in the cache, so that it looks made until it's sent.
+    /// Returns the id of the event shown for a change that creates one.
+    fn show_offline_edit(&mut self, edit: &Action) -> Option<String> {
+        let created_event = match edit {
+            Action::RespondToEvent {
+                event, response, ..
+            } => {
+                let calendar_id = self.calendar_client.event_calendar_id(event);
+                let mut event = *event.clone();
+                for attendee in event
+                    .attendees
+                    .iter_mut()
+                    .flatten()
+                    .filter(|attendee| attendee.self_ == Some(true))
+                {
+                    attendee.response_status = Some(response.as_str().to_string());
+                }
+                self.calendar_client.show_unsent_event(&calendar_id, event);
+                None
+            }
+            Action::QuickAdd { event, .. } => {
+                Some((String::from("primary"), event.to_calendar_event()))
+            }
+            Action::CreateEvent { calendar_id, event } => {
+                Some((calendar_id.clone(), *event.clone()))
+            }
+            // changes to more of a series can't be worked out without Google
+            Action::ChangeEvent {
+                change: EventChange::Delete(event),
+                scope: RecurrenceScope::ThisEvent,
+            } => {
+                if let Some(event_id) = &event.id {
+                    self.calendar_client.hide_unsent_event(event_id);
+                }
+                None
+            }
+            Action::ChangeEvent {
+                change: EventChange::Update { event, changes },
+                scope: RecurrenceScope::ThisEvent,
+            } => {
+                let calendar_id = self.calendar_client.event_calendar_id(event);
+                let updated_event = CalendarEvent {
+                    summary: changes.summary.clone().or(event.summary.clone()),
+                    location: changes.location.clone().or(event.location.clone()),
+                    start: changes.start.clone().or(event.start.clone()),
+                    end: changes.end.clone().or(event.end.clone()),
+                    ..*event.clone()
+                };
+                self.calendar_client
+                    .show_unsent_event(&calendar_id, updated_event);
+                None
+            }
+            _ => None,
+        };
+        let (calendar_id, event) = created_event?;
+        let event_id = format!("{UNSENT_EVENT_ID_PREFIX}{}", Utc::now().timestamp_micros());
+        self.calendar_client.show_unsent_event(
+            &calendar_id,
+            CalendarEvent {
+                id: Some(event_id.clone()),
+                ..event
+            },
+        );
+        Some(event_id)
+    }
+
+    /// Takes a change made while offline back out of the cache, putting back the events it
+    /// changed as they were
+    fn hide_offline_edit(&mut self, edit: &OfflineEdit) {
+        if let Some(event_id) = &edit.unsent_event_id {
+            self.calendar_client.hide_unsent_event(event_id);
+        }
+        match &edit.action {
+            Action::RespondToEvent { event, .. }
+            | Action::ChangeEvent {
+                change: EventChange::Delete(event) | EventChange::Update { event, .. },
+                scope: RecurrenceScope::ThisEvent,
+            } => {
+                let calendar_id = self.calendar_client.event_calendar_id(event);
+                self.calendar_client
+                    .show_unsent_event(&calendar_id, *event.clone());
+            }
+            _ => {}
+        }
+    }
+
+    /// Performs an action that needs the calendar api, telling the user if it failed
+    async fn perform_action(&mut self, action: Action) {
+        // an event made while offline has no id Google knows yet, so it can't be changed until
+        // it's sent
+        if let Action::RespondToEvent { event, .. }
+        | Action::ChangeEvent {
+            change: EventChange::Delete(event) | EventChange::Update { event, .. },
+            ..
+        } = &action
+        {
+            if event
+                .id
+                .as_ref()
+                .is_some_and(|event_id| event_id.starts_with(UNSENT_EVENT_ID_PREFIX))
+            {
+                self.message = Some(String::from(
+                    "That event hasn't been sent to Google yet, so it can't be changed",
+                ));
+                return;
+            }
+        }
+        let edit = action.clone();
+        match action {
+            Action::RespondToEvent {
+                event,
+                response,
+                comment,
+            } => {
+                let calendar_id = self.calendar_client.event_calendar_id(&event);
+                match self
+                    .calendar_client
+                    .respond_to_event(&event, response, comment)
+                    .await
+                {
+                    Ok(()) => self.record_change(calendar_id, *event),
+                    Err(err) => self.edit_failed("Failed to respond to the invitation", edit, err),
+                }
+                // the updated event may have moved or been hidden
+                self.clamp_event_selection();
+            }
+            Action::FindTime(request) => self.find_time(request).await,
+            Action::QuickAdd { text, event } => {
+                let result = match self.config.quick_add_parser {
+                    QuickAddParser::Google => {
+                        self.calendar_client.quick_add_event("primary", &text).await
+                    }
+                    QuickAddParser::Local => {
+                        self.calendar_client
+                            .insert_event("primary", event.to_calendar_event())
+                            .await
+                    }
+                };
+                match result {
+                    Ok(event) => self.event_created(String::from("primary"), event),
+                    Err(err) => self.edit_failed("Failed to create the event", edit, err),
+                }
+            }
+            Action::CreateEvent { calendar_id, event } => {
+                match self
+                    .calendar_client
+                    .insert_event(&calendar_id, *event)
+                    .await
+                {
+                    Ok(event) => self.event_created(calendar_id, event),
+                    Err(err) => self.edit_failed("Failed to create the event", edit, err),
+                }
+            }
+            Action::ChangeEvent {
+                change: EventChange::Delete(event),
+                scope,
+            } => {
+                let calendar_id = self.calendar_client.event_calendar_id(&event);
+                match self.calendar_client.delete_event(&event, scope).await {
+                    Ok(()) => {
+                        self.show_event_details_popup = false;
+                        self.changed_event("Deleted", calendar_id, *event, scope);
+                    }
+                    Err(err) => self.edit_failed("Failed to delete the event", edit, err),
+                }
+                self.clamp_event_selection();
+            }
+            Action::ChangeEvent {
+                change: EventChange::Update { event, changes },
+                scope,
+            } => {
+                let calendar_id = self.calendar_client.event_calendar_id(&event);
+                match self
+                    .calendar_client
+                    .update_event(&event, *changes, scope)
+                    .await
+                {
+                    Ok(()) => self.changed_event("Updated", calendar_id, *event, scope),
+                    Err(err) => self.edit_failed("Failed to update the event", edit, err),
+                }
+                // the updated event may have moved to another day
+                self.clamp_event_selection();
+            }
+            Action::Undo => self.undo_change().await,
+            Action::Redo => self.redo_change().await,
+        }
+    }
+
+    /// Tells the user about an event they created, and keeps it in the history
+    fn event_created(&mut self, calendar_id: String, event: CalendarEvent) {
+        self.message = Some(format!(
+            "Created \"{}\"",
+            event.summary.clone().unwrap_or_default()
+        ));
+        self.history.record(AppliedChange {
+            calendar_id,
+            before: None,
+            after: Some(event),
+        });
+    }
+
+    /// Tells the user that the event `before` was changed, described by `verb`, and keeps the
+    /// change in the history. Changes to more of a series can't be put back one event at a time,
+    /// so they can't be undone, and neither can the changes before them, which may have been to
+    /// the same events.
+    fn changed_event(
+        &mut self,
+        verb: &str,
+        calendar_id: String,
+        before: CalendarEvent,
+        scope: RecurrenceScope,
+    ) {
+        let summary = before.summary.clone().unwrap_or_default();
+        if scope == RecurrenceScope::ThisEvent {
+            self.message = Some(format!("{verb} \"{summary}\""));
+            self.record_change(calendar_id, before);
+        } else {
+            self.message = Some(format!(
+                "{verb} \"{summary}\", which can't be undone, nor can the changes before it"
+            ));
+            self.history.clear();
+        }
+    }
+
+    /// Keeps a change to the event `before` in the history, with what the cache says the event
+    /// became
+    fn record_change(&mut self, calendar_id: String, before: CalendarEvent) {
+        let after = before
+            .id
+            .as_deref()
+            .and_then(|event_id| self.calendar_client.get_event(event_id))
+            .cloned();
+        self.history.record(AppliedChange {
+            calendar_id,
+            before: Some(before),
+            after,
+        });
+    }
+
+    /// Reverts the last change that hasn't been undone
+    async fn undo_change(&mut self) {
+        let Some(change) = self.history.undo.pop() else {
+            return;
+        };
+        match change.undo(&mut self.calendar_client).await {
+            Ok(()) => {
+                self.message = Some(format!("Undid the change to \"{}\"", change.summary()));
+                self.history.redo.push(change);
+            }
+            Err(err) => {
+                self.history.undo.push(change);
+                self.report_error("Couldn't undo the change", err);
+            }
+        }
+        self.clamp_event_selection();
+    }
+
+    /// Makes the last undone change again
+    async fn redo_change(&mut self) {
+        let Some(change) = self.history.redo.pop() else {
+            return;
+        };
+        match change.redo(&mut self.calendar_client).await {
+            Ok(()) => {
+                self.message = Some(format!("Redid the change to \"{}\"", change.summary()));
+                self.history.undo.push(change);
+            }
+            Err(err) => {
+                self.history.redo.push(change);
+                self.report_error("Couldn't redo the change", err);
+            }
+        }
+        self.clamp_event_selection();
+    }
+
+    /// Looks for free slots that fit `request`, and shows them to the user
+    async fn find_time(&mut self, request: FindTimeRequest) {
+        let calendar_ids: Vec<String> = request
+            .calendars
+            .iter()
+            .map(|calendar| self.calendar_client.resolve_calendar_id(calendar))
+            .collect();
+
+        let busy = match request.source {
+            BusySource::Local => Ok((
+                self.calendar_client.local_busy_periods(
+                    &calendar_ids,
+                    request.first_day,
+                    request.last_day(),
+                ),
+                Vec::new(),
+            )),
+            BusySource::Google => {
+                // with no calendars given, look at all of ours
+                let calendar_ids = if calendar_ids.is_empty() {
+                    self.calendar_client
+                        .get_calendars()
+                        .iter()
+                        .filter_map(|calendar| calendar.id.clone())
+                        .collect()
+                } else {
+                    calendar_ids
+                };
+                let Some((time_min, time_max)) = request.time_bounds() else {
+                    self.message = Some(String::from("Those dates are out of range"));
+                    return;
+                };
+                self.calendar_client
+                    .query_free_busy(&calendar_ids, time_min, time_max)
+                    .await
+                    .map(|free_busy| {
+                        let busy = free_busy
+                            .busy
+                            .into_iter()
+                            .map(|(start, end)| {
+                                (start.with_timezone(&Local), end.with_timezone(&Local))
+                            })
+                            .collect::<Vec<_>>();
+                        (busy, free_busy.unchecked)
+                    })
+            }
+        };
+
+        match busy {
+            Ok((busy, unchecked)) => {
+                let slots = find_free_slots(&request, &busy, Local::now());
+                self.find_time_results = Some(FindTimeResults::new(request, slots, unchecked));
+            }
+            Err(err) => self.report_error("Failed to look up free time", err),
+        }
+    }
+
     /// Renders the user interface.
     ///
     /// This is where you add new widgets. See the following resources for more information:
     /// - <https://docs.rs/ratatui/latest/ratatui/widgets/index.html>
     /// - <https://github.com/ratatui/ratatui/tree/master/examples>
     fn draw(&mut self, frame: &mut Frame) {
-        self.draw_month_view(frame);
+        self.click_areas = ClickAreas::default();
+        let Some(screen_layout) = ScreenLayout::for_area(frame.area()) else {
+            return draw_too_small_message(frame);
+        };
+        let [area, status_bar_area] =
+            Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+        match screen_layout {
+            ScreenLayout::Month => self.draw_month_view(frame, area),
+            ScreenLayout::CompactMonth { side_by_side } => {
+                self.draw_compact_month_view(frame, area, side_by_side)
+            }
+            ScreenLayout::Agenda => self.draw_agenda_view(frame, area),
+        }
+
+        let account = self.calendar_client.cache.primary_account();
+        let status_bar = StatusBar::new(
+            screen_layout.name(),
+            &self.timezone,
+            &self.theme,
+            self.locale,
+        )
+        .last_synced(account.as_deref(), self.calendar_client.last_synced())
+        .syncing(
+            self.sync_task.as_ref().map(|_| self.sync_started),
+            self.sync_progress,
+        )
+        .offline(self.offline, self.offline_edits.len());
+        frame.render_widget(status_bar, status_bar_area);
 
         if self.show_inspect_day_popup {
-            let events = self.calendar_client.get_events_by_date(&self.currently_selected_date);
-            draw_inspect_day_popup(frame, events)
+            let events = self.visible_events_on_selected_date();
+            let selected_event = self.calendar_view_state.0.list_state().selected();
+            let inspect_day_popup =
+                draw_inspect_day_popup(frame, &events, selected_event, &self.theme, self.locale);
+            self.click_areas.inspect_day_popup = Some(inspect_day_popup);
+        }
+
+        if self.show_event_details_popup {
+            if let Some(event) = self.selected_event() {
+                let recurring_event = self.calendar_client.get_recurring_event(event);
+                let calendar = self.calendar_client.get_event_calendar(event);
+                draw_event_details_popup(
+                    frame,
+                    event,
+                    recurring_event,
+                    calendar,
+                    &self.theme,
+                    self.locale,
+                    self.event_details_scroll,
+                )
+            }
+        }
+
+        if let Some(results) = &mut self.find_time_results {
+            draw_find_time_results(frame, results, &self.theme, self.locale);
+        }
+
+        if self.show_error_log {
+            draw_error_log_popup(
+                frame,
+                &self.error_log,
+                &self.theme,
+                self.locale,
+                self.error_log_scroll,
+            );
+        }
+
+        if let Some((text, event)) = &self.quick_add_preview {
+            draw_quick_add_preview(
+                frame,
+                text,
+                event,
+                self.config.quick_add_parser,
+                &self.theme,
+                self.locale,
+            );
+        }
+
+        if let Some(form) = &self.find_time_form {
+            draw_find_time_form(frame, form, &self.theme);
+        }
+
+        if let Some((form, _)) = &self.edit_event_form {
+            draw_edit_event_form(frame, form, &self.theme);
+        }
+
+        if let Some((chooser, _)) = &mut self.scope_chooser {
+            draw_scope_chooser(frame, chooser, &self.theme);
+        }
+
+        if let Some((prompt, _)) = &self.prompt {
+            draw_text_prompt(frame, prompt, &self.theme);
+        }
+
+        if let Some(reminder) = self.reminders.first() {
+            self.draw_reminder_banner(frame, reminder);
+        }
+
+        if let Some(message) = &self.message {
+            let [_, message_area] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+            frame.render_widget(Clear, message_area);
+            frame.render_widget(
+                Text::from(message.as_str()).style(self.theme.status_bar),
+                message_area,
+            );
+        }
+
+        if let Some(error) = &self.error {
+            let [_, error_area] =
+                Layout::vertical([Constraint::Fill(1), Constraint::Length(1)]).areas(frame.area());
+            frame.render_widget(Clear, error_area);
+            // the hint stays on screen however long the error is
+            const HINT: &str = " [E] all errors";
+            let [text_area, hint_area] =
+                Layout::horizontal([Constraint::Fill(1), Constraint::Length(HINT.len() as u16)])
+                    .areas(error_area);
+            frame.render_widget(
+                Text::from(error.as_str()).style(self.theme.error),
+                text_area,
+            );
+            frame.render_widget(
+                Text::from(HINT).style(self.theme.error.add_modifier(Modifier::BOLD)),
+                hint_area,
+            );
         }
     }
 
-    fn draw_month_view(&mut self, frame: &mut Frame) {
+    fn draw_month_view(&mut self, frame: &mut Frame, area: Rect) {
         // split the area into header and body
-        let header_body_areas = Layout::vertical([
-            Constraint::Max(2),
-            Constraint::Fill(1)
-        ])
-        .split(frame.area());
+        let header_body_areas =
+            Layout::vertical([Constraint::Max(2), Constraint::Fill(1)]).split(area);
         self.draw_header(frame, header_body_areas[0]);
 
-        // the month is a 5x7 grid of CalendarDayWidgets
-        let row_constraints = Constraint::from_percentages(vec![20; 5]);
-        let col_constraints = Constraint::from_percentages(vec![100/7; 7]);
+        // leave room on the left for the week numbers, if they're enabled
+        let gutter_width = if self.config.show_week_numbers {
+            WEEK_NUMBER_GUTTER_WIDTH
+        } else {
+            0
+        };
+        let [gutter_area, grid_area] =
+            Layout::horizontal([Constraint::Length(gutter_width), Constraint::Fill(1)])
+                .areas(header_body_areas[1]);
+
+        // the grid has a row of weekday names above the days themselves
+        let weekday_days_constraints = [Constraint::Length(1), Constraint::Fill(1)];
+        let [weekdays_area, days_area] =
+            Layout::vertical(weekday_days_constraints).areas(grid_area);
+        let [_, gutter_area] = Layout::vertical(weekday_days_constraints).areas(gutter_area);
+
+        // the month is a (number of weeks)x7 grid of CalendarDayWidgets
+        let week_start = self.config.week_start;
+        let num_weeks = weeks_in_month(self.currently_selected_date, week_start);
+        let row_constraints = vec![Constraint::Ratio(1, num_weeks); num_weeks as usize];
+        let col_constraints = vec![Constraint::Ratio(1, 7); 7];
 
         let row_areas = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints(row_constraints.clone())
+            .split(days_area);
+
+        let gutter_row_areas = Layout::default()
             .direction(Direction::Vertical)
             .constraints(row_constraints)
-            .split(header_body_areas[1]);
+            .split(gutter_area);
 
-        let mut areas: [ [ Rect; 7 ] ; 5 ] = [ [ Rect::default(); 7]; 5];
+        // draw the names of the weekdays above their columns
+        let weekday_areas = Layout::default()
+            .direction(Direction::Horizontal)
+            .constraints(col_constraints.clone())
+            .split(weekdays_area);
 
-        // fill the areas array with the appropriate Rects
-        for (i, row) in row_areas.into_iter().enumerate() {
+        for (weekday, area) in ordered_weekdays(week_start)
+            .iter()
+            .zip(weekday_areas.iter())
+        {
+            let style = if is_weekend(*weekday) {
+                self.theme.weekend
+            } else {
+                self.theme.header
+            };
+            frame.render_widget(
+                Text::styled(weekday_short_name(*weekday, self.locale), style).centered(),
+                *area,
+            );
+        }
+
+        // fill the areas vec with the appropriate Rects
+        let mut areas: Vec<[Rect; 7]> = vec![[Rect::default(); 7]; num_weeks as usize];
+        for (i, row) in row_areas.iter().enumerate() {
             let col_areas = Layout::default()
                 .direction(Direction::Horizontal)
-                .constraints(col_constraints.clone())  // need a clone for each row
+                .constraints(col_constraints.clone()) // need a clone for each row
                 .split(*row);
 
-            for (j, area) in col_areas.into_iter().enumerate() {
+            for (j, area) in col_areas.iter().enumerate() {
                 areas[i][j] = *area;
             }
         }
 
-        // start at the first day of the month of the selected date
-        // TODO use from_ymp_opt instead
-        let mut date = self.currently_selected_date.clone().with_day(1)
-            .unwrap_or(NaiveDate::from_ymd(2025, 1, 1));
-
-        // we want the first calendar_day to be the previous monday (or date if date is a monday)
-        // TODO: choose between Monday and Sunday starting weeks
-        date = date -  chrono::TimeDelta::days(date.weekday().num_days_from_monday().into());
+        // start at the first day of the week containing the first day of the selected month
+        let first_of_month = self
+            .currently_selected_date
+            .with_day(1)
+            .unwrap_or(self.currently_selected_date);
+        let mut date = start_of_week(first_of_month, week_start);
 
         // render the calendar day widgets
-        for (_i, row) in areas.into_iter().enumerate() {
-            for (_j, area) in row.into_iter().enumerate() {
-                let calendar_events = match self.calendar_client.get_events_by_date(&date) {
-                    Some(calendar_events) => calendar_events,
-                    None => &Vec::<CalendarEvent>::new()
-                };
+        let theme = &self.theme;
+        for (row, gutter_row_area) in areas.into_iter().zip(gutter_row_areas.iter()) {
+            if self.config.show_week_numbers {
+                // offset by one line so the week number lines up with the day numbers
+                let week_number = Text::styled(
+                    format!("{:>2}", iso_week_number(date)),
+                    self.theme.secondary,
+                );
+                frame.render_widget(week_number, gutter_row_area.offset(Offset { x: 0, y: 1 }));
+            }
+
+            for area in row {
+                let calendar_events =
+                    visible_events(&self.calendar_client, &date, self.hide_declined_events);
 
-                let event_widgets: Vec<EventWidget> = calendar_events.iter().map(|cal_event| {
-                    EventWidget::new(
-                        cal_event, 
-                        &self.calendar_client.cache.colors
-                    )
-                })
+                let event_widgets: Vec<EventWidget> = calendar_events
+                    .iter()
+                    .map(|cal_event| {
+                        EventWidget::new(cal_event, self.event_color(cal_event), theme)
+                            .time_format(self.config.time_format, self.locale)
+                            .density(self.config.event_density)
+                    })
                     .collect();
 
                 let is_selected = { date == self.currently_selected_date };
-                let state: &mut CalendarDayWidgetState = if is_selected { self.calendar_view_state.0.state_mut() } else { self.calendar_view_state.1.state_mut() };
-                // update the selection status of the appropriate calendar_view_state 
+                let state: &mut CalendarDayWidgetState = if is_selected {
+                    self.calendar_view_state.0.state_mut()
+                } else {
+                    self.calendar_view_state.1.state_mut()
+                };
+                // update the selection status of the appropriate calendar_view_state
                 // TODO this is not efficient, we only see the selected date once per outer loop
                 if is_selected && !state.is_selected() {
                     state.set_selected(true)
@@ -181,20 +1144,107 @@ impl App {
                 }
 
                 frame.render_stateful_widget(
-                    CalendarDayWidget::new(
-                        event_widgets, 
-                        &date
-                    ),
+                    CalendarDayWidget::new(event_widgets, &date, theme)
+                        .adjacent_month(date.month() != self.currently_selected_date.month()),
                     area,
-                    state
+                    state,
                 );
+                self.click_areas.days.push((date, area));
 
                 // TODO: Use succ_opt instead
                 date = date.succ();
             }
         }
+    }
+
+    /// Draws a compact month with the events of the selected day beside it, or below it if
+    /// there's no room beside it
+    fn draw_compact_month_view(&mut self, frame: &mut Frame, area: Rect, side_by_side: bool) {
+        let [header_area, body_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(area);
+        self.draw_header(frame, header_area);
+
+        let week_start = self.config.week_start;
+        let busy_days = busy_days(self.currently_selected_date, week_start, |date| {
+            !visible_events(&self.calendar_client, date, self.hide_declined_events).is_empty()
+        });
+        let month = CompactMonthWidget::new(
+            self.currently_selected_date,
+            week_start,
+            &busy_days,
+            &self.theme,
+            self.locale,
+        );
+
+        let month_width = COMPACT_DAY_WIDTH * 7 + 1;
+        let [month_area, agenda_area] = if side_by_side {
+            Layout::horizontal([Constraint::Length(month_width), Constraint::Fill(1)])
+                .areas(body_area)
+        } else {
+            Layout::vertical([Constraint::Length(month.height() + 1), Constraint::Fill(1)])
+                .areas(body_area)
+        };
+        // leave a line between the header and the month
+        let month_area = month_area
+            .offset(Offset { x: 0, y: 1 })
+            .intersection(body_area);
+
+        self.click_areas.days = month.day_areas(month_area);
+        frame.render_widget(month, month_area);
+        self.draw_agenda(frame, agenda_area);
+    }
+
+    /// Draws only the events of the selected day, for the smallest terminals
+    fn draw_agenda_view(&mut self, frame: &mut Frame, area: Rect) {
+        let [header_area, agenda_area] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(area);
+        self.draw_header(frame, header_area);
+        self.draw_agenda(frame, agenda_area);
+    }
+
+    /// Draws the list of the selected day's events. The selected event is the same one that is
+    /// selected in the month grid.
+    fn draw_agenda(&mut self, frame: &mut Frame, area: Rect) {
+        let date = self.currently_selected_date;
+        let title = format!(
+            "{} {}",
+            weekday_short_name(date.weekday(), self.locale),
+            date_string(date, self.locale)
+        );
+        let events = visible_events(&self.calendar_client, &date, self.hide_declined_events)
+            .into_iter()
+            .map(|event| (event, self.event_color(event)))
+            .collect();
+        let agenda = AgendaWidget::new(
+            title,
+            events,
+            &self.theme,
+            self.config.time_format,
+            self.locale,
+        );
 
+        frame.render_stateful_widget(agenda, area, self.calendar_view_state.0.list_state_mut());
+        self.click_areas.agenda = Some(area);
+    }
+
+    /// Draws the oldest reminder across the top of the screen
+    fn draw_reminder_banner(&self, frame: &mut Frame, reminder: &Reminder) {
+        let [banner_area, _] =
+            Layout::vertical([Constraint::Length(1), Constraint::Fill(1)]).areas(frame.area());
+
+        let mut banner = format!(
+            "Reminder: {}  [z] snooze  [x] dismiss",
+            reminder.message(Local::now())
+        );
+        if self.reminders.len() > 1 {
+            banner += &format!("  (+{} more)", self.reminders.len() - 1);
+        }
 
+        frame.render_widget(Clear, banner_area);
+        frame.render_widget(
+            Text::from(banner).style(self.theme.status_bar.add_modifier(Modifier::BOLD)),
+            banner_area,
+        );
     }
 
     fn draw_header(&mut self, frame: &mut Frame, rect: Rect) {
@@ -203,16 +1253,33 @@ impl App {
             .direction(Direction::Horizontal)
             .constraints(constraints)
             .split(rect);
-        let month = month_to_str(self.currently_selected_date.month());
-        let year = self.currently_selected_date.year().to_string();
-
+        let title = Text::styled(
+            month_year_string(self.currently_selected_date, self.locale),
+            self.theme.header,
+        )
+        .centered();
+        // the full labels don't fit on narrow screens
+        let (previous, next) = if rect.width < MONTH_VIEW_MIN_SIZE.0 {
+            ("[p]", "[n]")
+        } else {
+            ("[p]revious", "[n]ext")
+        };
+        let previous = Text::from(previous).left_aligned();
+        let next = Text::from(next).right_aligned();
 
-        let title = Text::from(format!("{} {}", month, year))
-            .centered();
-        let previous = Text::from("[p]revious")
-            .left_aligned();
-        let next = Text::from("[n]ext")
-            .right_aligned();
+        // only the text itself is clickable, not the rest of its column
+        self.click_areas.previous_month = Rect {
+            width: (previous.width() as u16).min(layout[0].width),
+            height: 1,
+            ..layout[0]
+        };
+        let next_width = (next.width() as u16).min(layout[2].width);
+        self.click_areas.next_month = Rect {
+            x: layout[2].right() - next_width,
+            width: next_width,
+            height: 1,
+            ..layout[2]
+        };
 
         frame.render_widget(title, layout[1]);
         frame.render_widget(previous, layout[0]);
@@ -221,14 +1288,25 @@ impl App {
 
     /// Reads the crossterm events and updates the state of [`App`].
     ///
-    /// If your application needs to perform work in between handling events, you can use the
-    /// [`event::poll`] function to check if there are any events available with a timeout.
+    /// Waits at most [`TICK_RATE`] for an event, so that the main loop can do work between events.
     fn handle_crossterm_events(&mut self) -> Result<()> {
+        if !event::poll(TICK_RATE)? {
+            return Ok(());
+        }
+
         match event::read()? {
             // it's importantpanto check KeyEventKind::Press to avoid handling key release events
-            Event::Key(key) if key.kind == KeyEventKind::Press => self.on_key_event(key),
-            Event::Mouse(_) => {}
-            Event::Resize(_, _) => {}
+            Event::Key(key) if key.kind == KeyEventKind::Press => {
+                let quit_warned = self.quit_warned;
+                self.on_key_event(key);
+                // the warning about quitting only holds for the next key press
+                if quit_warned {
+                    self.quit_warned = false;
+                }
+            }
+            Event::Mouse(mouse) => self.on_mouse_event(mouse),
+            // the layout may change, and the old click areas don't line up with anything anymore
+            Event::Resize(_, _) => self.click_areas = ClickAreas::default(),
             _ => {}
         }
         Ok(())
@@ -236,21 +1314,838 @@ impl App {
 
     /// Handles the key events and updates the state of [`App`].
     fn on_key_event(&mut self, key: KeyEvent) {
+        if let (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) =
+            (key.modifiers, key.code)
+        {
+            return self.quit();
+        }
+
+        // any key press dismisses the message
+        self.message = None;
+        self.error = None;
+
+        if self.prompt.is_some() {
+            return self.on_prompt_key_event(key);
+        }
+        if self.find_time_form.is_some() {
+            return self.on_find_time_form_key_event(key);
+        }
+        if self.edit_event_form.is_some() {
+            return self.on_edit_event_form_key_event(key);
+        }
+        if self.scope_chooser.is_some() {
+            return self.on_scope_chooser_key_event(key);
+        }
+
+        if !self.reminders.is_empty() {
+            match key.code {
+                KeyCode::Char('z') => return self.snooze_reminder(),
+                KeyCode::Char('x') => return self.dismiss_reminder(),
+                _ => {}
+            }
+        }
+
+        // changes can be undone whatever is open, as long as nothing is being typed
+        match (key.modifiers, key.code) {
+            (KeyModifiers::CONTROL, KeyCode::Char('r')) => return self.redo(),
+            (_, KeyCode::Char('u')) => return self.undo(),
+            _ => {}
+        }
+
+        // popups take the keys first, the topmost popup first of all
+        if self.show_error_log {
+            return self.on_error_log_key_event(key);
+        }
+        if self.quick_add_preview.is_some() {
+            return self.on_quick_add_preview_key_event(key);
+        }
+        if self.find_time_results.is_some() {
+            return self.on_find_time_results_key_event(key);
+        }
+        if self.show_event_details_popup {
+            return self.on_event_details_key_event(key);
+        }
+        if self.show_inspect_day_popup {
+            return self.on_inspect_day_key_event(key);
+        }
+        if self.focus == Focus::Events {
+            return self.on_events_key_event(key);
+        }
+
         match (key.modifiers, key.code) {
             (_, KeyCode::Esc | KeyCode::Char('q'))
             | (KeyModifiers::CONTROL, KeyCode::Char('c') | KeyCode::Char('C')) => self.quit(),
             // Add other key handlers here.
-            (_, KeyCode::Char('p')) => self.currently_selected_date = self.currently_selected_date - Months::new(1),
-            (_, KeyCode::Char('n')) => self.currently_selected_date = self.currently_selected_date + Months::new(1),
+            (_, KeyCode::Char('p')) => {
+                self.currently_selected_date = self.currently_selected_date - Months::new(1)
+            }
+            (_, KeyCode::Char('n')) => {
+                self.currently_selected_date = self.currently_selected_date + Months::new(1)
+            }
             (_, KeyCode::Char('l')) => self.next_day(),
             (_, KeyCode::Char('h')) => self.previous_day(),
             (_, KeyCode::Char('j')) => self.next_week(),
             (_, KeyCode::Char('k')) => self.previous_week(),
-            (_, KeyCode::Char('i')) => self.show_inspect_day_popup = !self.show_inspect_day_popup,
+            (_, KeyCode::Char('i')) => self.open_inspect_day_popup(),
+            (_, KeyCode::Tab | KeyCode::Enter) => self.focus_events(),
+            (_, KeyCode::Char('H')) => self.toggle_hide_declined_events(),
+            (_, KeyCode::Char('f')) => {
+                self.find_time_form = Some(FindTimeForm::new(
+                    &self.config,
+                    self.currently_selected_date,
+                ))
+            }
+            (_, KeyCode::Char('a')) => {
+                self.prompt = Some((TextPrompt::new("Quick add"), PromptPurpose::QuickAdd))
+            }
+            (_, KeyCode::Char('E')) => self.open_error_log(),
+            (_, KeyCode::Char('r')) => self.sync(),
+            _ => {}
+        }
+    }
+
+    /// Queues undoing the last change, if there is one
+    fn undo(&mut self) {
+        if self.history.undo.is_empty() {
+            self.message = Some(String::from("Nothing to undo"));
+            return;
+        }
+        self.pending_actions.push_back(Action::Undo);
+    }
+
+    /// Queues redoing the last undone change, if there is one
+    fn redo(&mut self) {
+        if self.history.redo.is_empty() {
+            self.message = Some(String::from("Nothing to redo"));
+            return;
+        }
+        self.pending_actions.push_back(Action::Redo);
+    }
+
+    /// Syncs with Google now, rather than waiting for the next sync
+    fn sync(&mut self) {
+        if self.sync_task.is_some() {
+            self.message = Some(String::from("Already syncing"));
+            return;
+        }
+        self.start_sync();
+    }
+
+    fn open_error_log(&mut self) {
+        self.show_error_log = true;
+        self.error_log_scroll = 0;
+    }
+
+    fn on_error_log_key_event(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('E') => self.show_error_log = false,
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.error_log_scroll = self.error_log_scroll.saturating_add(1)
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.error_log_scroll = self.error_log_scroll.saturating_sub(1)
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles mouse events. Clicking a day selects it, and double clicking opens the inspect day
+    /// popup. Clicking an event opens its details. The scroll wheel scrolls the event list under
+    /// the pointer, or changes the month.
+    fn on_mouse_event(&mut self, mouse: MouseEvent) {
+        let (column, row) = (mouse.column, mouse.row);
+
+        // forms and prompts are keyboard only
+        if self.prompt.is_some()
+            || self.find_time_form.is_some()
+            || self.edit_event_form.is_some()
+            || self.scope_chooser.is_some()
+            || self.quick_add_preview.is_some()
+            || self.find_time_results.is_some()
+        {
+            return;
+        }
+
+        let is_double_click = match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                let now = Instant::now();
+                let is_double_click =
+                    self.last_click
+                        .is_some_and(|(time, last_column, last_row)| {
+                            now - time < DOUBLE_CLICK_INTERVAL
+                                && (last_column, last_row) == (column, row)
+                        });
+                // a third click starts again rather than being another double click
+                self.last_click = if is_double_click {
+                    None
+                } else {
+                    Some((now, column, row))
+                };
+                is_double_click
+            }
+            _ => false,
+        };
+
+        if self.show_event_details_popup {
+            match mouse.kind {
+                MouseEventKind::ScrollDown => {
+                    self.event_details_scroll = self.event_details_scroll.saturating_add(1)
+                }
+                MouseEventKind::ScrollUp => {
+                    self.event_details_scroll = self.event_details_scroll.saturating_sub(1)
+                }
+                _ => {}
+            }
+            return;
+        }
+
+        if let Some((popup_area, offset)) = self.click_areas.inspect_day_popup {
+            match mouse.kind {
+                MouseEventKind::ScrollDown => {
+                    self.calendar_view_state.0.list_state_mut().select_next()
+                }
+                MouseEventKind::ScrollUp => self
+                    .calendar_view_state
+                    .0
+                    .list_state_mut()
+                    .select_previous(),
+                MouseEventKind::Down(MouseButton::Left)
+                    if popup_area.contains(Position::new(column, row)) =>
+                {
+                    // the first row is the popup's border
+                    if let Some(index) = row.checked_sub(popup_area.y + 1) {
+                        if (index as usize + offset) < self.visible_events_on_selected_date().len()
+                        {
+                            self.calendar_view_state
+                                .0
+                                .list_state_mut()
+                                .select(Some(index as usize + offset));
+                            self.open_event_details_popup();
+                        }
+                    }
+                }
+                MouseEventKind::Down(MouseButton::Left) => self.close_inspect_day_popup(),
+                _ => {}
+            }
+            self.clamp_event_selection();
+            return;
+        }
+
+        let position = Position::new(column, row);
+        if let Some(agenda_area) = self
+            .click_areas
+            .agenda
+            .filter(|area| area.contains(position))
+        {
+            return self.on_agenda_mouse_event(mouse.kind, agenda_area, row);
+        }
+
+        let day = self
+            .click_areas
+            .days
+            .iter()
+            .find(|(_, area)| area.contains(position))
+            .copied();
+
+        match mouse.kind {
+            MouseEventKind::Down(MouseButton::Left)
+                if self.click_areas.previous_month.contains(position) =>
+            {
+                self.currently_selected_date = self.currently_selected_date - Months::new(1)
+            }
+            MouseEventKind::Down(MouseButton::Left)
+                if self.click_areas.next_month.contains(position) =>
+            {
+                self.currently_selected_date = self.currently_selected_date + Months::new(1)
+            }
+            MouseEventKind::Down(MouseButton::Left) => {
+                if let Some((date, area)) = day {
+                    self.on_day_clicked(date, area, row, is_double_click);
+                }
+            }
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => {
+                let scroll_down = mouse.kind == MouseEventKind::ScrollDown;
+                match day {
+                    // scrolling over the selected day moves through its events
+                    Some((date, _))
+                        if date == self.currently_selected_date && self.focus == Focus::Events =>
+                    {
+                        let list_state = self.calendar_view_state.0.list_state_mut();
+                        if scroll_down {
+                            list_state.select_next()
+                        } else {
+                            list_state.select_previous()
+                        }
+                        self.clamp_event_selection();
+                    }
+                    // without a selected event it scrolls the day's cell instead
+                    Some((date, _)) if date == self.currently_selected_date => {
+                        let offset = self.calendar_view_state.0.list_state_mut().offset_mut();
+                        *offset = if scroll_down {
+                            offset.saturating_add(1)
+                        } else {
+                            offset.saturating_sub(1)
+                        };
+                    }
+                    _ if scroll_down => {
+                        self.currently_selected_date = self.currently_selected_date + Months::new(1)
+                    }
+                    _ => {
+                        self.currently_selected_date = self.currently_selected_date - Months::new(1)
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles mouse events over the list of the selected day's events. Clicking an event opens
+    /// its details, and the scroll wheel moves through the events.
+    fn on_agenda_mouse_event(&mut self, kind: MouseEventKind, area: Rect, row: u16) {
+        match kind {
+            MouseEventKind::Down(MouseButton::Left) => {
+                // the first row is the border
+                let offset = self.calendar_view_state.0.list_state().offset();
+                let clicked_event = row
+                    .checked_sub(area.y + 1)
+                    .map(|index| index as usize + offset)
+                    .filter(|index| *index < self.visible_events_on_selected_date().len());
+                if let Some(index) = clicked_event {
+                    self.focus = Focus::Events;
+                    self.calendar_view_state
+                        .0
+                        .list_state_mut()
+                        .select(Some(index));
+                    self.open_event_details_popup();
+                }
+            }
+            MouseEventKind::ScrollDown if self.focus == Focus::Events => {
+                self.calendar_view_state.0.list_state_mut().select_next()
+            }
+            MouseEventKind::ScrollUp if self.focus == Focus::Events => self
+                .calendar_view_state
+                .0
+                .list_state_mut()
+                .select_previous(),
+            MouseEventKind::ScrollDown | MouseEventKind::ScrollUp => self.focus_events(),
+            _ => {}
+        }
+        self.clamp_event_selection();
+    }
+
+    /// Selects the clicked day. Clicking one of its events opens the details of the event, and
+    /// double clicking the day opens the inspect day popup.
+    fn on_day_clicked(&mut self, date: NaiveDate, area: Rect, row: u16, is_double_click: bool) {
+        let was_selected = date == self.currently_selected_date;
+        self.currently_selected_date = date;
+
+        // the first row of the cell is its border, then there's one event per row, and maybe a
+        // footer saying how many more there are
+        let offset = if was_selected {
+            self.calendar_view_state.0.list_state().offset()
+        } else {
+            0
+        };
+        let events = self.visible_events_on_selected_date().len();
+        let clicked_event = row
+            .checked_sub(area.y + 1)
+            .map(|index| index as usize)
+            .filter(|index| *index < event_rows(area, events))
+            .map(|index| index + offset)
+            .filter(|index| *index < events);
+
+        if is_double_click {
+            self.focus_days();
+            self.open_inspect_day_popup();
+            return;
+        }
+
+        match clicked_event {
+            Some(index) => {
+                self.focus = Focus::Events;
+                self.calendar_view_state
+                    .0
+                    .list_state_mut()
+                    .select(Some(index));
+                self.open_event_details_popup();
+            }
+            None => self.focus_days(),
+        }
+    }
+
+    /// Handles key events while the user is checking what we understood from their quick add text
+    fn on_quick_add_preview_key_event(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.quick_add_preview = None,
+            KeyCode::Char('e') => {
+                if let Some((text, _)) = self.quick_add_preview.take() {
+                    self.prompt = Some((
+                        TextPrompt::with_input("Quick add", text),
+                        PromptPurpose::QuickAdd,
+                    ));
+                }
+            }
+            KeyCode::Enter => {
+                if let Some((text, event)) = self.quick_add_preview.take() {
+                    self.pending_actions.push_back(Action::QuickAdd {
+                        text,
+                        event: Box::new(event),
+                    });
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles key events while the user is choosing which events of a series to change
+    fn on_scope_chooser_key_event(&mut self, key: KeyEvent) {
+        let Some((chooser, _)) = &mut self.scope_chooser else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.scope_chooser = None,
+            KeyCode::Char('j') | KeyCode::Down => chooser.list_state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => chooser.list_state.select_previous(),
+            KeyCode::Enter => {
+                if let Some((chooser, change)) = self.scope_chooser.take() {
+                    if let Some(scope) = chooser.selected_scope() {
+                        self.pending_actions
+                            .push_back(Action::ChangeEvent { change, scope });
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    /// Opens a form to edit the title, time and location of the selected event
+    fn edit_selected_event(&mut self) {
+        let Some(event) = self.selected_event() else {
+            return;
+        };
+        self.edit_event_form = Some((EditEventForm::new(event), Box::new(event.clone())));
+    }
+
+    /// Handles key events while the edit event form is open
+    fn on_edit_event_form_key_event(&mut self, key: KeyEvent) {
+        let Some((form, _)) = &mut self.edit_event_form else {
+            return;
+        };
+
+        match form.on_key_event(key) {
+            FormEvent::Editing => {}
+            FormEvent::Cancelled => self.edit_event_form = None,
+            FormEvent::Submitted => match form.to_changes() {
+                Ok(changes) => {
+                    if let Some((_, event)) = self.edit_event_form.take() {
+                        self.change_event(EventChange::Update {
+                            event,
+                            changes: Box::new(changes),
+                        });
+                    }
+                }
+                // leave the form open so the mistake can be fixed
+                Err(err) => self.message = Some(err),
+            },
+        }
+    }
+
+    /// Asks for confirmation to delete the selected event
+    fn delete_selected_event(&mut self) {
+        if let Some(event) = self.selected_event() {
+            self.change_event(EventChange::Delete(Box::new(event.clone())));
+        }
+    }
+
+    /// Asks which events of the series `change` applies to if the event is recurring. Deleting
+    /// an event that isn't recurring is confirmed too, updating one happens straight away.
+    fn change_event(&mut self, change: EventChange) {
+        let (event, verb) = match &change {
+            EventChange::Delete(event) => (event, "Delete"),
+            EventChange::Update { event, .. } => (event, "Change"),
+        };
+
+        let title = format!(
+            "{} \"{}\"",
+            verb,
+            event.summary.as_deref().unwrap_or_default()
+        );
+        let scopes = if self.calendar_client.get_recurring_event(event).is_some() {
+            vec![
+                RecurrenceScope::ThisEvent,
+                RecurrenceScope::ThisAndFollowing,
+                RecurrenceScope::AllEvents,
+            ]
+        } else if let EventChange::Delete(_) = change {
+            vec![RecurrenceScope::ThisEvent]
+        } else {
+            self.pending_actions.push_back(Action::ChangeEvent {
+                change,
+                scope: RecurrenceScope::ThisEvent,
+            });
+            return;
+        };
+        self.scope_chooser = Some((ScopeChooser::new(title, scopes), change));
+    }
+
+    /// Handles key events while the find time form is open
+    fn on_find_time_form_key_event(&mut self, key: KeyEvent) {
+        let Some(form) = &mut self.find_time_form else {
+            return;
+        };
+
+        match form.on_key_event(key) {
+            FormEvent::Editing => {}
+            FormEvent::Cancelled => self.find_time_form = None,
+            FormEvent::Submitted => match form.to_request(&self.config.working_days) {
+                Ok(request) => {
+                    self.find_time_form = None;
+                    self.pending_actions.push_back(Action::FindTime(request));
+                }
+                // leave the form open so the mistake can be fixed
+                Err(err) => self.message = Some(err),
+            },
+        }
+    }
+
+    /// Handles key events while the free slots are being shown
+    fn on_find_time_results_key_event(&mut self, key: KeyEvent) {
+        let Some(results) = &mut self.find_time_results else {
+            return;
+        };
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.find_time_results = None,
+            KeyCode::Char('j') | KeyCode::Down => results.list_state.select_next(),
+            KeyCode::Char('k') | KeyCode::Up => results.list_state.select_previous(),
+            KeyCode::Enter => {
+                let Some(slot) = results.selected_slot().cloned() else {
+                    return;
+                };
+                // anyone we looked up by email is invited, but our own calendars aren't
+                let our_calendar_ids: Vec<&String> = self
+                    .calendar_client
+                    .get_calendars()
+                    .iter()
+                    .filter_map(|calendar| calendar.id.as_ref())
+                    .collect();
+                let attendees = results
+                    .request
+                    .calendars
+                    .iter()
+                    .filter(|calendar| {
+                        calendar.contains('@') && !our_calendar_ids.contains(calendar)
+                    })
+                    .cloned()
+                    .collect();
+                self.find_time_results = None;
+                self.prompt = Some((
+                    TextPrompt::new("Title of the new event"),
+                    PromptPurpose::NewEventTitle { slot, attendees },
+                ));
+            }
+            _ => {}
+        }
+    }
+
+    /// Handles key events while a text prompt is open
+    fn on_prompt_key_event(&mut self, key: KeyEvent) {
+        let Some((prompt, _)) = &mut self.prompt else {
+            return;
+        };
+
+        match prompt.on_key_event(key) {
+            PromptEvent::Editing => {}
+            PromptEvent::Cancelled => self.prompt = None,
+            PromptEvent::Submitted(input) => {
+                if let Some((_, purpose)) = self.prompt.take() {
+                    self.on_prompt_submitted(purpose, input);
+                }
+            }
+        }
+    }
+
+    fn on_prompt_submitted(&mut self, purpose: PromptPurpose, input: String) {
+        match purpose {
+            PromptPurpose::ResponseComment(response) => {
+                let comment = Some(input).filter(|comment| !comment.is_empty());
+                self.respond_to_selected_event(response, comment);
+            }
+            PromptPurpose::QuickAdd => match parse_quick_add(&input, Local::now()) {
+                Some(event) => self.quick_add_preview = Some((input, event)),
+                None => self.message = Some(String::from("Quick add needs a title")),
+            },
+            PromptPurpose::NewEventTitle { slot, attendees } => {
+                let event = CalendarEvent {
+                    summary: Some(input),
+                    start: Some(EventDateTime {
+                        date_time: Some(slot.start.to_utc()),
+                        ..Default::default()
+                    }),
+                    end: Some(EventDateTime {
+                        date_time: Some(slot.end.to_utc()),
+                        ..Default::default()
+                    }),
+                    attendees: (!attendees.is_empty()).then(|| {
+                        attendees
+                            .into_iter()
+                            .map(|email| EventAttendee {
+                                email: Some(email),
+                                ..Default::default()
+                            })
+                            .collect()
+                    }),
+                    ..Default::default()
+                };
+                self.pending_actions.push_back(Action::CreateEvent {
+                    calendar_id: String::from("primary"),
+                    event: Box::new(event),
+                });
+            }
+        }
+    }
+
+    /// Handles key events while moving between the events of the selected day
+    fn on_events_key_event(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Tab => self.focus_days(),
+            KeyCode::Char('q') => self.quit(),
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.calendar_view_state.0.list_state_mut().select_next()
+            }
+            KeyCode::Char('k') | KeyCode::Up => self
+                .calendar_view_state
+                .0
+                .list_state_mut()
+                .select_previous(),
+            KeyCode::Char('i') => self.open_inspect_day_popup(),
+            KeyCode::Char('e') => self.edit_selected_event(),
+            KeyCode::Char('X') => self.delete_selected_event(),
+            KeyCode::Enter => self.open_event_details_popup(),
+            KeyCode::Char('r') => self.sync(),
+            _ => {}
+        }
+        self.clamp_event_selection();
+    }
+
+    /// Moves focus into the event list of the selected day, if it has any events
+    fn focus_events(&mut self) {
+        self.calendar_view_state.0.list_state_mut().select_first();
+        self.clamp_event_selection();
+        if self.selected_event().is_some() {
+            self.focus = Focus::Events;
+        }
+    }
+
+    /// Moves focus back to the days, deselecting the selected event
+    fn focus_days(&mut self) {
+        self.focus = Focus::Days;
+        self.calendar_view_state.0.list_state_mut().select(None);
+    }
+
+    /// Handles key events while the inspect day popup is open
+    fn on_inspect_day_key_event(&mut self, key: KeyEvent) {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') | KeyCode::Char('i') => {
+                self.close_inspect_day_popup()
+            }
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.calendar_view_state.0.list_state_mut().select_next()
+            }
+            KeyCode::Char('k') | KeyCode::Up => self
+                .calendar_view_state
+                .0
+                .list_state_mut()
+                .select_previous(),
+            KeyCode::Char('e') => self.edit_selected_event(),
+            KeyCode::Char('X') => self.delete_selected_event(),
+            KeyCode::Enter => self.open_event_details_popup(),
+            _ => {}
+        }
+        self.clamp_event_selection();
+    }
+
+    /// Handles key events while the event details popup is open
+    fn on_event_details_key_event(&mut self, key: KeyEvent) {
+        let response = match key.code {
+            KeyCode::Char('a' | 'A') => Some(ResponseStatus::Accepted),
+            KeyCode::Char('d' | 'D') => Some(ResponseStatus::Declined),
+            KeyCode::Char('t' | 'T') => Some(ResponseStatus::Tentative),
+            _ => None,
+        };
+        if let (Some(response), KeyCode::Char(c)) = (response, key.code) {
+            // upper case responds with a comment
+            if c.is_uppercase() {
+                let title = format!("Comment to send with your response ({})", response.as_str());
+                self.prompt = Some((
+                    TextPrompt::new(title),
+                    PromptPurpose::ResponseComment(response),
+                ));
+            } else {
+                self.respond_to_selected_event(response, None);
+            }
+            return;
+        }
+
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.show_event_details_popup = false,
+            KeyCode::Char('e') => self.edit_selected_event(),
+            KeyCode::Char('X') => self.delete_selected_event(),
+            KeyCode::Char('j') | KeyCode::Down => {
+                self.event_details_scroll = self.event_details_scroll.saturating_add(1)
+            }
+            KeyCode::Char('k') | KeyCode::Up => {
+                self.event_details_scroll = self.event_details_scroll.saturating_sub(1)
+            }
+            _ => {}
+        }
+    }
+
+    /// Shows reminders that have become due since the last tick, and alerts the user to them
+    fn update_reminders(&mut self) {
+        let now = Local::now();
+        self.snoozed_reminders.retain(|_, until| *until > now);
+
+        let due_reminders = due_reminders(&self.calendar_client, now);
+
+        // reminders of events that have ended aren't due anymore
+        self.reminders.retain(|reminder| {
+            due_reminders
+                .iter()
+                .any(|due_reminder| due_reminder.key == reminder.key)
+        });
+
+        for reminder in due_reminders {
+            let is_showing = self
+                .reminders
+                .iter()
+                .any(|showing| showing.key == reminder.key);
+            if is_showing || self.snoozed_reminders.contains_key(&reminder.key) {
+                continue;
+            }
+            self.config.notification.notify(&reminder.message(now));
+            self.reminders.push(reminder);
+        }
+    }
+
+    /// Hides the oldest reminder for [`Config::snooze_minutes`]
+    fn snooze_reminder(&mut self) {
+        if self.reminders.is_empty() {
+            return;
+        }
+        let reminder = self.reminders.remove(0);
+        let until = Local::now() + TimeDelta::minutes(self.config.snooze_minutes.into());
+        self.snoozed_reminders.insert(reminder.key, until);
+    }
+
+    /// Hides the oldest reminder for good
+    fn dismiss_reminder(&mut self) {
+        if self.reminders.is_empty() {
+            return;
+        }
+        let reminder = self.reminders.remove(0);
+        if let Err(err) = self
+            .calendar_client
+            .dismiss_reminder(&reminder.key, reminder.event_start)
+        {
+            self.report_error("Couldn't save the dismissed reminder", err);
+        }
+    }
+
+    /// Queues our response to the selected event, if we were invited to it
+    fn respond_to_selected_event(&mut self, response: ResponseStatus, comment: Option<String>) {
+        let Some(event) = self.selected_event() else {
+            return;
+        };
+
+        if event.self_response_status().is_none() {
+            self.message = Some(String::from("You aren't a guest of this event"));
+            return;
+        }
+
+        self.pending_actions.push_back(Action::RespondToEvent {
+            event: Box::new(event.clone()),
+            response,
+            comment,
+        });
+    }
+
+    /// Shows or hides the events whose invitation we've declined
+    fn toggle_hide_declined_events(&mut self) {
+        self.hide_declined_events = !self.hide_declined_events;
+        self.clamp_event_selection();
+    }
+
+    /// Opens the inspect day popup, selecting the first event of the day if none is selected
+    fn open_inspect_day_popup(&mut self) {
+        self.show_inspect_day_popup = true;
+        let list_state = self.calendar_view_state.0.list_state_mut();
+        if list_state.selected().is_none() {
+            list_state.select_first();
+        }
+        self.clamp_event_selection();
+    }
+
+    /// Closes the inspect day popup. The event selection is kept only if the events have focus.
+    fn close_inspect_day_popup(&mut self) {
+        self.show_inspect_day_popup = false;
+        if self.focus == Focus::Days {
+            self.calendar_view_state.0.list_state_mut().select(None);
+        }
+    }
+
+    /// Opens the event details popup for the selected event, if there is one
+    fn open_event_details_popup(&mut self) {
+        if self.selected_event().is_some() {
+            self.event_details_scroll = 0;
+            self.show_event_details_popup = true;
+        }
+    }
+
+    /// Makes sure the selected event is one of the events on the selected day
+    fn clamp_event_selection(&mut self) {
+        let num_events = self.visible_events_on_selected_date().len();
+        let list_state = self.calendar_view_state.0.list_state_mut();
+        match list_state.selected() {
+            _ if num_events == 0 => list_state.select(None),
+            Some(selected) if selected >= num_events => list_state.select(Some(num_events - 1)),
             _ => {}
         }
     }
 
+    /// Returns the selected event on the selected day, if there is one
+    fn selected_event(&self) -> Option<&CalendarEvent> {
+        let selected = self.calendar_view_state.0.list_state().selected()?;
+        self.visible_events_on_selected_date()
+            .get(selected)
+            .copied()
+    }
+
+    /// Returns the color to draw `event` in
+    fn event_color(&self, event: &CalendarEvent) -> EventColor {
+        if !self.theme.event_colors {
+            return self.theme.default_event;
+        }
+        let calendar = self.calendar_client.get_event_calendar(event);
+        event_color(
+            event,
+            calendar,
+            &self.calendar_client.cache.colors,
+            self.color_support,
+        )
+        .unwrap_or(self.theme.default_event)
+    }
+
+    /// Returns the events on the selected day that aren't hidden
+    fn visible_events_on_selected_date(&self) -> Vec<&CalendarEvent> {
+        visible_events(
+            &self.calendar_client,
+            &self.currently_selected_date,
+            self.hide_declined_events,
+        )
+    }
+
     /// sets [`self.currently_selected_date`] to the next day
     fn next_day(&mut self) {
         self.currently_selected_date = self.currently_selected_date + Days::new(1)
@@ -271,14 +2166,44 @@ impl App {
         self.currently_selected_date = self.currently_selected_date - Days::new(7)
     }
 
-    /// Set running to false to quit the application.
+    /// Set running to false to quit the application. Changes made while offline would be lost,
+    /// so the user is warned first and has to quit again.
     fn quit(&mut self) {
+        if !self.offline_edits.is_empty() && !self.quit_warned {
+            self.quit_warned = true;
+            let edits = match self.offline_edits.len() {
+                1 => String::from("1 edit hasn't"),
+                edits => format!("{edits} edits haven't"),
+            };
+            self.message = Some(format!(
+                "{edits} been sent to Google and will be lost, quit again to quit anyway"
+            ));
+            return;
+        }
+        // the cache may have been saved with the unsent changes shown in it
+        if !self.offline_edits.is_empty() {
+            while let Some(edit) = self.offline_edits.pop_back() {
+                self.hide_offline_edit(&edit);
+            }
+            _ = self.calendar_client.save_cache();
+        }
         self.running = false;
     }
-
-
 }
 
+/// Tells the user that the terminal needs to be bigger
+fn draw_too_small_message(frame: &mut Frame) {
+    let (width, height) = MIN_SIZE;
+    let message = Text::from(vec![
+        Line::from("Terminal too small"),
+        Line::from(format!("needs at least {width}x{height}")),
+    ]);
+    let area = frame.area();
+    let [area] = Layout::vertical([Constraint::Length(2)])
+        .flex(Flex::Center)
+        .areas(area);
+    frame.render_widget(message.centered(), area);
+}
 
 ////////////////////////////////////////////
 //                                        //
@@ -286,3 +2211,19 @@ impl App {
 //                                        //
 ////////////////////////////////////////////
 
+/// Returns the events on `date`, leaving out declined events if they're hidden
+fn visible_events<'a>(
+    calendar_client: &'a CalendarClient,
+    date: &NaiveDate,
+    hide_declined_events: bool,
+) -> Vec<&'a CalendarEvent> {
+    calendar_client
+        .get_events_by_date(date)
+        .map(|events| {
+            events
+                .iter()
+                .filter(|event| !(hide_declined_events && event.is_declined()))
+                .collect()
+        })
+        .unwrap_or_default()
+}
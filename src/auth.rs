@@ -0,0 +1,234 @@
+use std::path::Path;
+
+use clap::Subcommand;
+use color_eyre::{eyre::eyre, Result};
+use google_calendar3::yup_oauth2::storage::TokenInfo;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::app::{determine_cache_path, determine_token_path};
+use crate::config::Config;
+use crate::google_cal_backend::{restrict_to_owner, CalendarCache, CalendarClient, LoginFlow};
+use crate::login::printed_login_flow;
+
+/// Tells us which scopes a token was actually granted, which can be fewer than we asked for
+const TOKEN_INFO_URL: &str = "https://oauth2.googleapis.com/tokeninfo";
+const REVOKE_URL: &str = "https://oauth2.googleapis.com/revoke";
+/// Where users can see and remove the apps with access to their account
+const PERMISSIONS_URL: &str = "https://myaccount.google.com/permissions";
+
+#[derive(Debug, Subcommand)]
+pub enum AuthCommand {
+    /// Log in to Google, unless you're logged in already
+    Login,
+    /// Revoke the login with Google, and delete it and the cached calendars
+    Logout,
+    /// Show the account you're logged in as and the scopes it was granted
+    Status,
+}
+
+/// A token as yup_oauth2 keeps it in the token file
+#[derive(Deserialize)]
+struct StoredToken {
+    scopes: Vec<String>,
+    token: TokenInfo,
+}
+
+#[derive(Deserialize)]
+struct TokenInfoResponse {
+    /// the granted scopes, separated by spaces
+    scope: String,
+}
+
+/// Runs `talendar auth <command>`
+pub async fn run(command: AuthCommand, config: &Config, json: bool) -> Result<()> {
+    match command {
+        AuthCommand::Login => login(config, json).await,
+        AuthCommand::Logout => logout(json).await,
+        AuthCommand::Status => status(json).await,
+    }
+}
+
+async fn login(config: &Config, json: bool) -> Result<()> {
+    let token_path = determine_token_path()?;
+    let calendar_client = CalendarClient::new(
+        determine_cache_path()?,
+        &token_path,
+        printed_login_flow(config),
+    )
+    .await?;
+    calendar_client
+        .authenticate()
+        .await
+        .map_err(|err| eyre!("Login failed: {err}"))?;
+    restrict_to_owner(&token_path);
+
+    let account = calendar_client
+        .account()
+        .await
+        .ok()
+        .flatten()
+        .or_else(|| calendar_client.cache.primary_account());
+    if json {
+        println!("{}", json!({ "logged_in": true, "account": account }));
+    } else {
+        println!(
+            "Logged in as {}",
+            account.as_deref().unwrap_or("an unknown account")
+        );
+    }
+    Ok(())
+}
+
+async fn logout(json: bool) -> Result<()> {
+    let token_path = determine_token_path()?;
+    let cache_path = determine_cache_path()?;
+    if !token_path.exists() {
+        if json {
+            println!("{}", json!({ "logged_out": false }));
+        } else {
+            println!("Not logged in");
+        }
+        return Ok(());
+    }
+
+    let account = CalendarCache::load(&cache_path).primary_account();
+
+    // revoking the refresh token revokes the access tokens made from it too
+    let mut revoke_error = None;
+    for stored in read_tokens(&token_path) {
+        let token = stored.token.refresh_token.or(stored.token.access_token);
+        if let Some(token) = token {
+            if let Err(err) = revoke(&token).await {
+                revoke_error = Some(err);
+            }
+        }
+    }
+
+    std::fs::remove_file(&token_path)
+        .map_err(|err| eyre!("Couldn't delete {}: {err}", token_path.display()))?;
+    // the cache belongs to the account, so it goes too
+    for path in [cache_path.with_extension("json.tmp"), cache_path] {
+        if path.exists() {
+            std::fs::remove_file(&path)
+                .map_err(|err| eyre!("Couldn't delete {}: {err}", path.display()))?;
+        }
+    }
+
+    if let Some(err) = &revoke_error {
+        eprintln!(
+            "Couldn't revoke the login with Google ({err}). It's been deleted here, but you can also \
+             remove talendar's access at {PERMISSIONS_URL}"
+        );
+    }
+    if json {
+        println!(
+            "{}",
+            json!({ "logged_out": true, "account": account, "revoked": revoke_error.is_none() })
+        );
+    } else {
+        println!("Logged out of {}", account.as_deref().unwrap_or("Google"));
+    }
+    Ok(())
+}
+
+async fn status(json: bool) -> Result<()> {
+    let token_path = determine_token_path()?;
+    let Some(stored) = read_tokens(&token_path).into_iter().next() else {
+        if json {
+            println!("{}", json!({ "logged_in": false }));
+        } else {
+            println!("Not logged in. Run `talendar auth login` to log in.");
+        }
+        return Ok(());
+    };
+
+    // status shouldn't start a login, so only the token we have is used
+    let calendar_client = CalendarClient::new(
+        determine_cache_path()?,
+        &token_path,
+        LoginFlow::ExistingToken,
+    )
+    .await?;
+    let mut account = calendar_client.cache.primary_account();
+    let mut scopes = stored.scopes;
+    let valid = match calendar_client.access_token().await {
+        Ok(Some(access_token)) => {
+            if let Ok(Some(fetched_account)) = calendar_client.account().await {
+                account = Some(fetched_account);
+            }
+            // fall back to the scopes we asked for if we can't find out which were granted
+            if let Ok(granted_scopes) = granted_scopes(&access_token).await {
+                scopes = granted_scopes;
+            }
+            true
+        }
+        _ => false,
+    };
+
+    if json {
+        println!(
+            "{}",
+            json!({
+                "logged_in": true,
+                "valid": valid,
+                "account": account,
+                "scopes": scopes,
+                "token_file": token_path.display().to_string(),
+            })
+        );
+        return Ok(());
+    }
+
+    println!(
+        "Logged in as {}",
+        account.as_deref().unwrap_or("an unknown account")
+    );
+    if !valid {
+        println!(
+            "The login has expired or been revoked. Run `talendar auth login` to log in again."
+        );
+    }
+    println!("Scopes:");
+    for scope in scopes {
+        println!("  {scope}");
+    }
+    println!("Token file: {}", token_path.display());
+    Ok(())
+}
+
+/// Reads the tokens in the token file, or none if there isn't one or it can't be read
+fn read_tokens(token_path: &Path) -> Vec<StoredToken> {
+    std::fs::read(token_path)
+        .ok()
+        .and_then(|contents| serde_json::from_slice(&contents).ok())
+        .unwrap_or_default()
+}
+
+/// Asks Google which scopes `access_token` was granted
+async fn granted_scopes(access_token: &str) -> Result<Vec<String>> {
+    let response: TokenInfoResponse = reqwest::Client::new()
+        .get(TOKEN_INFO_URL)
+        .query(&[("access_token", access_token)])
+        .send()
+        .await?
+        .error_for_status()?
+        .json()
+        .await?;
+    Ok(response
+        .scope
+        .split_whitespace()
+        .map(String::from)
+        .collect())
+}
+
+/// Tells Google to stop accepting `token`
+async fn revoke(token: &str) -> Result<()> {
+    reqwest::Client::new()
+        .post(REVOKE_URL)
+        .form(&[("token", token)])
+        .send()
+        .await?
+        .error_for_status()?;
+    Ok(())
+}
@@ -1,33 +1,151 @@
+use crate::event_widget::EventWidget;
+use crate::theme::Theme;
+use crate::utils::{is_weekend, truncate_line};
 use chrono::{Datelike, NaiveDate};
 use ratatui::{
-    buffer::Buffer, layout::Rect, style::{Color, Modifier, Style}, widgets::{Block, BorderType, List, ListState, StatefulWidget, Widget}
+    buffer::Buffer,
+    layout::{Alignment, Rect},
+    style::Style,
+    text::{Line, Text},
+    widgets::{Block, List, ListState, StatefulWidget, Widget},
 };
-use crate::event_widget::EventWidget;
 
 pub struct CalendarDayWidget<'a> {
     events: Vec<EventWidget<'a>>,
-    date: &'a NaiveDate
+    date: &'a NaiveDate,
+    theme: &'a Theme,
+    /// is the day from the month before or after the one shown?
+    is_adjacent_month: bool,
 }
 
 impl Widget for CalendarDayWidget<'_> {
-    fn render(self, area: Rect, buf: &mut Buffer) { 
-        let mut title = self.date.day().to_string();
-        if &chrono::offset::Local::now().date_naive() == self.date {
-            title += " [Today]"
-        }
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        let block = self.block();
+        let list = List::new(event_items(&self.events, area));
+        render_events(
+            list,
+            self.events.len(),
+            block,
+            self.theme.secondary,
+            area,
+            buf,
+            &mut ListState::default(),
+        );
+    }
+}
 
-        let block = Block::bordered().title(title);
-        let list = List::new(&self.events).block(block);
+/// Returns how many events fit in a cell drawn in `area`. When they don't all fit, the last row
+/// is taken by the "+N more" footer.
+pub fn event_rows(area: Rect, events: usize) -> usize {
+    let rows = area.height.saturating_sub(2) as usize;
+    if events > rows {
+        rows.saturating_sub(1)
+    } else {
+        rows
+    }
+}
 
-        // render is implemented for both StatefulWidget and Widget, so we need to tell
-        // Rust which one to use explicitly. This is equivalent to list.render(area, buf)
-        Widget::render(list, area, buf);
+/// Renders `list` inside `block`, with a footer saying how many events are scrolled out of view
+/// if they don't all fit
+fn render_events(
+    list: List,
+    events: usize,
+    block: Block,
+    footer_style: Style,
+    area: Rect,
+    buf: &mut Buffer,
+    list_state: &mut ListState,
+) {
+    let inner = block.inner(area);
+    block.render(area, buf);
+
+    let rows = event_rows(area, events);
+    // don't scroll past the last event, which would leave empty rows at the bottom
+    let offset = list_state.offset_mut();
+    *offset = (*offset).min(events.saturating_sub(rows));
+
+    let list_area = Rect {
+        height: rows as u16,
+        ..inner
+    };
+    // render is implemented for both StatefulWidget and Widget, so we need to tell
+    // Rust which one to use explicitly. This is equivalent to list.render(area, buf, state)
+    StatefulWidget::render(list, list_area, buf, list_state);
+
+    if rows < events {
+        let above = list_state.offset();
+        let below = events - rows - above;
+        let arrows = match (above > 0, below > 0) {
+            (true, true) => "↕ ",
+            (true, false) => "↑ ",
+            _ => "↓ ",
+        };
+        let footer = Line::styled(format!("{arrows}+{} more", events - rows), footer_style)
+            .alignment(Alignment::Right);
+        let footer_area = Rect {
+            y: list_area.bottom(),
+            height: 1,
+            ..inner
+        };
+        footer.render(footer_area, buf);
     }
 }
 
+/// Returns the lines of the events, cut short with an ellipsis where they don't fit inside the
+/// border of a cell drawn in `area`
+fn event_items<'a>(events: &[EventWidget<'a>], area: Rect) -> Vec<Text<'a>> {
+    let width = area.width.saturating_sub(2) as usize;
+    events
+        .iter()
+        .map(|event| {
+            let text = Text::from(event);
+            Text::from_iter(
+                text.lines
+                    .into_iter()
+                    .map(|line| truncate_line(line, width)),
+            )
+        })
+        .collect()
+}
+
 impl<'a> CalendarDayWidget<'a> {
-    pub fn new(events: Vec<EventWidget<'a>>, date: &'a NaiveDate) -> Self {
-        CalendarDayWidget { events , date }
+    pub fn new(events: Vec<EventWidget<'a>>, date: &'a NaiveDate, theme: &'a Theme) -> Self {
+        CalendarDayWidget {
+            events,
+            date,
+            theme,
+            is_adjacent_month: false,
+        }
+    }
+
+    pub fn adjacent_month(mut self, is_adjacent_month: bool) -> Self {
+        self.is_adjacent_month = is_adjacent_month;
+        self
+    }
+
+    /// Returns the border of the day, titled with the day of the month
+    fn block(&self) -> Block<'a> {
+        let is_today = &chrono::offset::Local::now().date_naive() == self.date;
+
+        let mut title_style = Style::default();
+        if is_weekend(self.date.weekday()) {
+            title_style = title_style.patch(self.theme.weekend);
+        }
+        if self.is_adjacent_month {
+            title_style = title_style.patch(self.theme.adjacent_month);
+        }
+        if is_today {
+            title_style = title_style.patch(self.theme.today);
+        }
+
+        let mut title = self.date.day().to_string();
+        if is_today {
+            title += " [Today]";
+        }
+
+        Block::bordered()
+            .border_style(self.theme.border)
+            .title(Line::styled(title, title_style))
     }
 }
 
@@ -38,22 +156,32 @@ impl<'a> CalendarDayWidget<'a> {
 pub struct CalendarDayWidgetState {
     is_selected: bool,
     list_state: ListState,
+    /// the day the list was last rendered for, so that a new day starts scrolled to the top
+    date: Option<NaiveDate>,
 }
 
 impl CalendarDayWidgetState {
     pub fn default() -> CalendarDayWidgetState {
-        CalendarDayWidgetState{ is_selected: false, list_state: ListState::default() }
+        CalendarDayWidgetState {
+            is_selected: false,
+            list_state: ListState::default(),
+            date: None,
+        }
     }
 
     pub fn state_mut(&mut self) -> &mut Self {
         self
     }
 
+    pub fn list_state(&self) -> &ListState {
+        &self.list_state
+    }
+
     pub fn list_state_mut(&mut self) -> &mut ListState {
         &mut self.list_state
     }
 
-    pub fn is_selected(&mut self) -> & bool {
+    pub fn is_selected(&mut self) -> &bool {
         &self.is_selected
     }
 
@@ -66,28 +194,30 @@ impl StatefulWidget for CalendarDayWidget<'_> {
     type State = CalendarDayWidgetState;
 
     fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
-        let mut title = self.date.day().to_string();
-        if &chrono::offset::Local::now().date_naive() == self.date {
-            title += " [Today]";
-        }
-
-        let mut block = Block::bordered().title(title);
+        let mut block = self.block();
 
         // if this widget is selected, then highlight it
-        if state.is_selected == true {
+        if state.is_selected {
             block = block
-                .border_style(Style::default()
-                    .fg(Color::LightBlue)
-                    .add_modifier(Modifier::BOLD)
-                )
-                .border_type(BorderType::Thick);
+                .border_style(self.theme.selected_border)
+                .border_type(self.theme.selected_border_type);
         }
 
-        let list = List::new(&self.events).block(block);
-
+        if state.date != Some(*self.date) {
+            state.date = Some(*self.date);
+            *state.list_state.offset_mut() = 0;
+        }
 
-        // render is implemented for both StatefulWidget and Widget, so we need to tell
-        // Rust which one to use explicitly. This is equivalent to list.render(area, buf, state)
-        StatefulWidget::render(list, area, buf, state.list_state_mut())
+        // the selected event, if there is one, is highlighted
+        let list = List::new(event_items(&self.events, area)).highlight_style(self.theme.selection);
+        render_events(
+            list,
+            self.events.len(),
+            block,
+            self.theme.secondary,
+            area,
+            buf,
+            state.list_state_mut(),
+        )
     }
 }
@@ -0,0 +1,305 @@
+use chrono::{DateTime, Datelike, Days, Local, Locale, NaiveDate};
+use clap::{Parser, Subcommand};
+use color_eyre::{eyre::eyre, Result};
+use google_calendar3::api::{CalendarListEntry, Event as CalendarEvent};
+use serde_json::{json, Value};
+
+use crate::app::{determine_cache_path, determine_token_path};
+use crate::auth::{self, AuthCommand};
+use crate::config::Config;
+use crate::error::{is_network_error, TalendarError};
+use crate::google_cal_backend::{CalendarCache, CalendarClient, CalendarEventExt, LoginFlow};
+use crate::quick_add::{parse_date, parse_quick_add, QuickAddParser};
+use crate::status::status;
+use crate::utils::{date_string, time_string, weekday_short_name};
+
+const DEFAULT_TITLE: &str = "No title";
+
+/// A terminal calendar for Google Calendar. Without a command, the calendar is opened in the
+/// terminal.
+#[derive(Debug, Parser)]
+#[command(version, about)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+
+    /// Print machine readable JSON instead of text
+    #[arg(long, global = true)]
+    pub json: bool,
+}
+
+#[derive(Debug, Subcommand)]
+pub enum Command {
+    /// List the events in the cache between two days
+    Agenda {
+        /// The first day: "today", "tomorrow", a weekday name or a date like 2025-01-31
+        #[arg(long, default_value = "today")]
+        from: String,
+        /// How many days to list
+        #[arg(long, default_value_t = 7)]
+        days: u32,
+    },
+    /// Fetch the latest events from Google Calendar into the cache
+    Sync,
+    /// Create an event from a line of text like "Lunch with Sam tomorrow 1pm @Cafe"
+    Add {
+        /// The event, in the same format as quick add in the calendar
+        #[arg(required = true)]
+        text: Vec<String>,
+        /// The name or id of the calendar to add the event to
+        #[arg(long, default_value = "primary")]
+        calendar: String,
+    },
+    /// List the calendars in the cache
+    Calendars,
+    /// Print the current or next event for a status bar, without syncing. With --json, prints a
+    /// waybar custom module.
+    Status,
+    /// Log in to Google, log out, or show who you're logged in as
+    Auth {
+        #[command(subcommand)]
+        command: AuthCommand,
+    },
+}
+
+/// Runs `command` without the terminal interface, printing the result to stdout
+pub async fn run(command: Command, json: bool) -> Result<()> {
+    let (config, config_error) = Config::load_or_default();
+    if let Some(err) = config_error {
+        eprintln!("{err}. Using the default config.");
+    }
+    let locale = config.locale();
+
+    match command {
+        // status bars run this every few seconds, so it reads the cache without connecting to the
+        // api
+        Command::Status => {
+            let cache = CalendarCache::load(&determine_cache_path()?);
+            let status = status(&cache, &config.status, Local::now(), locale);
+            if json {
+                println!("{}", status.to_waybar_json());
+            } else {
+                println!("{}", status.text);
+            }
+            Ok(())
+        }
+        Command::Auth { command } => auth::run(command, &config, json).await,
+        // these only read the cache too, so they work offline and without logging in
+        Command::Agenda { from, days } => {
+            let cache = CalendarCache::load(&determine_cache_path()?);
+            let today = Local::now().date_naive();
+            let first_day = parse_date(&from.to_lowercase(), today).ok_or(eyre!(
+                "\"{from}\" isn't a day like today, tomorrow, monday or 2025-01-31"
+            ))?;
+            agenda(&cache, &config, first_day, days, json, locale)
+        }
+        Command::Calendars => {
+            let cache = CalendarCache::load(&determine_cache_path()?);
+            let calendars = &cache.calendars;
+            if json {
+                let calendars: Vec<Value> = calendars.iter().map(calendar_json).collect();
+                println!("{}", Value::from(calendars));
+            } else {
+                for calendar in calendars {
+                    let primary = if calendar.primary == Some(true) {
+                        " (primary)"
+                    } else {
+                        ""
+                    };
+                    println!(
+                        "{}{}  {}",
+                        calendar_name(calendar),
+                        primary,
+                        calendar.id.as_deref().unwrap_or_default()
+                    );
+                }
+            }
+            Ok(())
+        }
+        Command::Sync => {
+            let mut calendar_client = connect().await?;
+            let failed = calendar_client.sync(config.sync_concurrency).await?;
+            let calendars = calendar_client.get_calendars().len() - failed.len();
+            if json {
+                let failed: Vec<_> = failed.iter()
+                    .map(|calendar| json!({ "calendar": calendar.name, "error": calendar.error.to_string() }))
+                    .collect();
+                println!(
+                    "{}",
+                    json!({ "synced_calendars": calendars, "failed_calendars": failed })
+                );
+            } else {
+                println!("Synced {calendars} calendars");
+                for calendar in &failed {
+                    eprintln!("Couldn't sync {}: {}", calendar.name, calendar.error);
+                }
+            }
+            Ok(())
+        }
+        Command::Add { text, calendar } => {
+            let mut calendar_client = connect().await?;
+            let text = text.join(" ");
+            let calendar_id = calendar_client.resolve_calendar_id(&calendar);
+            let event = match config.quick_add_parser {
+                QuickAddParser::Google => {
+                    calendar_client.quick_add_event(&calendar_id, &text).await?
+                }
+                QuickAddParser::Local => {
+                    let event = parse_quick_add(&text, Local::now())
+                        .ok_or(eyre!("The event needs a title"))?;
+                    calendar_client
+                        .insert_event(&calendar_id, event.to_calendar_event())
+                        .await?
+                }
+            };
+            if json {
+                println!("{}", event_json(&calendar_client.cache, &event));
+            } else {
+                println!(
+                    "Created \"{}\" at {}",
+                    event.summary.as_deref().unwrap_or(DEFAULT_TITLE),
+                    event.get_start_string(locale)
+                );
+            }
+            Ok(())
+        }
+    }
+}
+
+/// Connects to the api with the token from `talendar auth login`. Commands don't log in
+/// themselves, since nobody may be there to, like when they're run by cron.
+async fn connect() -> Result<CalendarClient> {
+    let calendar_client = CalendarClient::new(
+        determine_cache_path()?,
+        &determine_token_path()?,
+        LoginFlow::ExistingToken,
+    )
+    .await?;
+    match calendar_client.authenticate().await {
+        Ok(()) => Ok(calendar_client),
+        Err(err) if is_network_error(err.as_ref()) => Err(TalendarError::Network(err).into()),
+        Err(_) => Err(eyre!("Not logged in, run `talendar auth login`")),
+    }
+}
+
+/// Prints the events from the start of `first_day` until the end of the `days`th day, grouped
+/// by day. Events that started before `first_day` are listed under it.
+fn agenda(
+    cache: &CalendarCache,
+    config: &Config,
+    first_day: NaiveDate,
+    days: u32,
+    json: bool,
+    locale: Locale,
+) -> Result<()> {
+    let local_midnight = |date: NaiveDate| {
+        date.and_hms_opt(0, 0, 0)?
+            .and_local_timezone(Local)
+            .earliest()
+    };
+    let last_day = first_day
+        .checked_add_days(Days::new(days.into()))
+        .ok_or(eyre!("Too many days"))?;
+    let (Some(start), Some(end)) = (local_midnight(first_day), local_midnight(last_day)) else {
+        return Err(eyre!("Those days are out of range"));
+    };
+
+    let events: Vec<&CalendarEvent> = cache
+        .events_between(start, end)
+        .into_iter()
+        .filter(|event| !(config.hide_declined_events && event.is_declined()))
+        .collect();
+
+    if json {
+        let events: Vec<Value> = events
+            .iter()
+            .map(|event| event_json(cache, event))
+            .collect();
+        println!("{}", Value::from(events));
+        return Ok(());
+    }
+
+    let mut current_day = None;
+    for event in events {
+        let Some((event_start, event_end)) = event.get_local_bounds() else {
+            continue;
+        };
+        let day = event_start.max(start).date_naive();
+        if current_day != Some(day) {
+            if current_day.is_some() {
+                println!();
+            }
+            println!(
+                "{} {}",
+                weekday_short_name(day.weekday(), locale),
+                date_string(day, locale)
+            );
+            current_day = Some(day);
+        }
+        println!(
+            "  {:<20} {}",
+            event_time_string(event, event_start, event_end, locale),
+            event.summary.as_deref().unwrap_or(DEFAULT_TITLE)
+        );
+    }
+
+    if current_day.is_none() {
+        println!("No events");
+    }
+    Ok(())
+}
+
+/// Returns e.g. "09:00 - 10:30", or "all day" for all day events
+fn event_time_string(
+    event: &CalendarEvent,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    locale: Locale,
+) -> String {
+    if event.is_all_day() {
+        String::from("all day")
+    } else {
+        format!(
+            "{} - {}",
+            time_string(&start, locale),
+            time_string(&end, locale)
+        )
+    }
+}
+
+fn event_json(cache: &CalendarCache, event: &CalendarEvent) -> Value {
+    let bounds = event.get_local_bounds();
+    let calendar = cache.event_calendar(event);
+    json!({
+        "id": event.id,
+        "summary": event.summary,
+        "start": bounds.map(|(start, _)| start.to_rfc3339()),
+        "end": bounds.map(|(_, end)| end.to_rfc3339()),
+        "all_day": event.is_all_day(),
+        "location": event.location,
+        "calendar_id": calendar.and_then(|calendar| calendar.id.clone()),
+        "calendar": calendar.map(calendar_name),
+        "response_status": event.self_response_status(),
+        "recurring_event_id": event.recurring_event_id,
+        "link": event.html_link,
+    })
+}
+
+fn calendar_json(calendar: &CalendarListEntry) -> Value {
+    json!({
+        "id": calendar.id,
+        "name": calendar_name(calendar),
+        "primary": calendar.primary == Some(true),
+        "access_role": calendar.access_role,
+        "background_color": calendar.background_color,
+    })
+}
+
+fn calendar_name(calendar: &CalendarListEntry) -> String {
+    calendar
+        .summary_override
+        .as_ref()
+        .or(calendar.summary.as_ref())
+        .cloned()
+        .unwrap_or_default()
+}
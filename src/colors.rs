@@ -0,0 +1,213 @@
+use google_calendar3::api::{CalendarListEntry, Colors, Event as CalendarEvent};
+use ratatui::style::Color;
+use serde::Deserialize;
+
+/// Colors are kept between these lightnesses so that they show up on both light and dark
+/// terminals
+const MIN_LIGHTNESS: f64 = 0.35;
+const MAX_LIGHTNESS: f64 = 0.7;
+
+/// The levels of each channel in the 6x6x6 color cube of a 256 color terminal
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+/// The 16 basic terminal colors, with the values xterm uses for them
+const ANSI_COLORS: [(Color, (u8, u8, u8)); 16] = [
+    (Color::Black, (0, 0, 0)),
+    (Color::Red, (205, 0, 0)),
+    (Color::Green, (0, 205, 0)),
+    (Color::Yellow, (205, 205, 0)),
+    (Color::Blue, (0, 0, 238)),
+    (Color::Magenta, (205, 0, 205)),
+    (Color::Cyan, (0, 205, 205)),
+    (Color::Gray, (229, 229, 229)),
+    (Color::DarkGray, (127, 127, 127)),
+    (Color::LightRed, (255, 0, 0)),
+    (Color::LightGreen, (0, 255, 0)),
+    (Color::LightYellow, (255, 255, 0)),
+    (Color::LightBlue, (92, 92, 255)),
+    (Color::LightMagenta, (255, 0, 255)),
+    (Color::LightCyan, (0, 255, 255)),
+    (Color::White, (255, 255, 255)),
+];
+
+/// How many colors the terminal can show
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum ColorSupport {
+    /// work it out from the `COLORTERM` and `TERM` environment variables
+    #[default]
+    #[serde(rename = "auto")]
+    Auto,
+    #[serde(rename = "truecolor")]
+    TrueColor,
+    #[serde(rename = "256")]
+    Ansi256,
+    #[serde(rename = "16")]
+    Ansi16,
+}
+
+impl ColorSupport {
+    /// Resolves `Auto` to what the terminal says it supports
+    pub fn detect(self) -> Self {
+        if self != ColorSupport::Auto {
+            return self;
+        }
+        let colorterm = std::env::var("COLORTERM").unwrap_or_default();
+        let term = std::env::var("TERM").unwrap_or_default();
+        if colorterm == "truecolor" || colorterm == "24bit" {
+            ColorSupport::TrueColor
+        } else if term.contains("256color") {
+            ColorSupport::Ansi256
+        } else {
+            ColorSupport::Ansi16
+        }
+    }
+}
+
+/// The color of an event, and the color of text drawn on top of it
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct EventColor {
+    pub color: Color,
+    pub text: Color,
+}
+
+/// Works out the color to draw `event` in: its own color if it has one, otherwise the color of
+/// `calendar`. Returns `None` if neither has a color, so the theme's default should be used.
+/// The color is made readable on light and dark terminals and reduced to what the terminal can
+/// show.
+pub fn event_color(
+    event: &CalendarEvent,
+    calendar: Option<&CalendarListEntry>,
+    colors: &Colors,
+    support: ColorSupport,
+) -> Option<EventColor> {
+    let event_color = event
+        .color_id
+        .as_ref()
+        .and_then(|color_id| colors.event.as_ref()?.get(color_id)?.background.as_deref());
+    let calendar_color = calendar.and_then(|calendar| {
+        calendar.background_color.as_deref().or_else(|| {
+            let color_id = calendar.color_id.as_ref()?;
+            colors
+                .calendar
+                .as_ref()?
+                .get(color_id)?
+                .background
+                .as_deref()
+        })
+    });
+
+    let rgb = with_readable_lightness(event_color.or(calendar_color).and_then(parse_hex)?);
+    Some(EventColor {
+        color: downsample(rgb, support),
+        text: text_color_on(rgb),
+    })
+}
+
+/// Parses a color like "#a4bdfc"
+fn parse_hex(hex: &str) -> Option<(u8, u8, u8)> {
+    let hex = hex.strip_prefix('#')?;
+    if hex.len() != 6 {
+        return None;
+    }
+    let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2)?, 16).ok();
+    Some((channel(0)?, channel(2)?, channel(4)?))
+}
+
+/// Returns the closest color the terminal can show
+fn downsample(rgb: (u8, u8, u8), support: ColorSupport) -> Color {
+    let (r, g, b) = rgb;
+    match support.detect() {
+        ColorSupport::TrueColor | ColorSupport::Auto => Color::Rgb(r, g, b),
+        ColorSupport::Ansi256 => {
+            // the nearest color in the cube, or on the grey ramp if that's closer
+            let cube_index = |channel: u8| {
+                (0..CUBE_LEVELS.len())
+                    .min_by_key(|i| CUBE_LEVELS[*i].abs_diff(channel))
+                    .unwrap_or_default()
+            };
+            let (ri, gi, bi) = (cube_index(r), cube_index(g), cube_index(b));
+            let cube_rgb = (CUBE_LEVELS[ri], CUBE_LEVELS[gi], CUBE_LEVELS[bi]);
+
+            let grey_index =
+                ((u32::from(r) + u32::from(g) + u32::from(b)) / 3).saturating_sub(8) / 10;
+            let grey_index = grey_index.min(23) as u8;
+            let grey = 8 + 10 * grey_index;
+
+            if distance(rgb, (grey, grey, grey)) < distance(rgb, cube_rgb) {
+                Color::Indexed(232 + grey_index)
+            } else {
+                Color::Indexed(16 + 36 * ri as u8 + 6 * gi as u8 + bi as u8)
+            }
+        }
+        ColorSupport::Ansi16 => ANSI_COLORS
+            .iter()
+            .min_by_key(|(_, ansi_rgb)| distance(rgb, *ansi_rgb))
+            .map(|(color, _)| *color)
+            .unwrap_or(Color::Reset),
+    }
+}
+
+/// The squared distance between two colors
+fn distance(a: (u8, u8, u8), b: (u8, u8, u8)) -> u32 {
+    let channel = |a: u8, b: u8| u32::from(a.abs_diff(b)).pow(2);
+    channel(a.0, b.0) + channel(a.1, b.1) + channel(a.2, b.2)
+}
+
+/// Returns black or white, whichever is easier to read on `rgb`
+fn text_color_on(rgb: (u8, u8, u8)) -> Color {
+    let (r, g, b) = rgb;
+    let luminance = 0.2126 * f64::from(r) + 0.7152 * f64::from(g) + 0.0722 * f64::from(b);
+    if luminance > 128.0 {
+        Color::Black
+    } else {
+        Color::White
+    }
+}
+
+/// Lightens colors that are too dark to see on a dark terminal, and darkens colors that are too
+/// light to see on a light one, keeping their hue and saturation
+fn with_readable_lightness(rgb: (u8, u8, u8)) -> (u8, u8, u8) {
+    let (r, g, b) = (
+        f64::from(rgb.0) / 255.0,
+        f64::from(rgb.1) / 255.0,
+        f64::from(rgb.2) / 255.0,
+    );
+    let max = r.max(g).max(b);
+    let min = r.min(g).min(b);
+    let lightness = (max + min) / 2.0;
+    if (MIN_LIGHTNESS..=MAX_LIGHTNESS).contains(&lightness) {
+        return rgb;
+    }
+
+    // convert to HSL, clamp the lightness and convert back
+    let delta = max - min;
+    let saturation = if delta == 0.0 {
+        0.0
+    } else {
+        delta / (1.0 - (2.0 * lightness - 1.0).abs())
+    };
+    let hue = if delta == 0.0 {
+        0.0
+    } else if max == r {
+        60.0 * ((g - b) / delta).rem_euclid(6.0)
+    } else if max == g {
+        60.0 * ((b - r) / delta + 2.0)
+    } else {
+        60.0 * ((r - g) / delta + 4.0)
+    };
+
+    let lightness = lightness.clamp(MIN_LIGHTNESS, MAX_LIGHTNESS);
+    let chroma = (1.0 - (2.0 * lightness - 1.0).abs()) * saturation;
+    let x = chroma * (1.0 - ((hue / 60.0).rem_euclid(2.0) - 1.0).abs());
+    let (r, g, b) = match hue as u32 / 60 {
+        0 => (chroma, x, 0.0),
+        1 => (x, chroma, 0.0),
+        2 => (0.0, chroma, x),
+        3 => (0.0, x, chroma),
+        4 => (x, 0.0, chroma),
+        _ => (chroma, 0.0, x),
+    };
+    let m = lightness - chroma / 2.0;
+    let channel = |value: f64| ((value + m) * 255.0).round().clamp(0.0, 255.0) as u8;
+    (channel(r), channel(g), channel(b))
+}
@@ -0,0 +1,207 @@
+use std::collections::HashSet;
+
+use chrono::{Datelike, Local, Locale, NaiveDate, Weekday};
+use google_calendar3::api::Event as CalendarEvent;
+use ratatui::{
+    buffer::Buffer,
+    layout::{Alignment, Rect},
+    style::{Modifier, Style},
+    text::{Line, Span, Text},
+    widgets::{Block, List, ListState, Paragraph, StatefulWidget, Widget},
+};
+
+use crate::colors::EventColor;
+use crate::event_widget::{EventDensity, EventWidget, TimeFormat};
+use crate::theme::Theme;
+use crate::utils::{
+    is_weekend, ordered_weekdays, start_of_week, truncate_line, weekday_short_name, weeks_in_month,
+};
+
+/// Each day of the compact month is this many columns wide
+pub const COMPACT_DAY_WIDTH: u16 = 4;
+
+/// A month small enough for a narrow terminal: just the day numbers, with the days that have
+/// events underlined
+pub struct CompactMonthWidget<'a> {
+    selected_date: NaiveDate,
+    week_start: Weekday,
+    /// the days that have events we'd show
+    busy_days: &'a HashSet<NaiveDate>,
+    theme: &'a Theme,
+    locale: Locale,
+}
+
+impl<'a> CompactMonthWidget<'a> {
+    pub fn new(
+        selected_date: NaiveDate,
+        week_start: Weekday,
+        busy_days: &'a HashSet<NaiveDate>,
+        theme: &'a Theme,
+        locale: Locale,
+    ) -> Self {
+        CompactMonthWidget {
+            selected_date,
+            week_start,
+            busy_days,
+            theme,
+            locale,
+        }
+    }
+
+    /// The number of rows the month needs: one for the weekday names and one for each week
+    pub fn height(&self) -> u16 {
+        weeks_in_month(self.selected_date, self.week_start) as u16 + 1
+    }
+
+    /// Returns where each day is drawn when the month is rendered in `area`
+    pub fn day_areas(&self, area: Rect) -> Vec<(NaiveDate, Rect)> {
+        let first_of_month = self.selected_date.with_day(1).unwrap_or(self.selected_date);
+        let first_day = start_of_week(first_of_month, self.week_start);
+        let weeks = weeks_in_month(self.selected_date, self.week_start);
+
+        first_day
+            .iter_days()
+            .take(weeks as usize * 7)
+            .enumerate()
+            .map(|(i, date)| {
+                let (week, weekday) = ((i / 7) as u16, (i % 7) as u16);
+                let day_area = Rect::new(
+                    area.x + weekday * COMPACT_DAY_WIDTH,
+                    area.y + 1 + week,
+                    COMPACT_DAY_WIDTH,
+                    1,
+                );
+                (date, day_area.intersection(area))
+            })
+            .collect()
+    }
+}
+
+impl Widget for CompactMonthWidget<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        for (i, weekday) in ordered_weekdays(self.week_start).iter().enumerate() {
+            let name: String = weekday_short_name(*weekday, self.locale)
+                .chars()
+                .take(2)
+                .collect();
+            let name_area = Rect::new(
+                area.x + i as u16 * COMPACT_DAY_WIDTH,
+                area.y,
+                COMPACT_DAY_WIDTH,
+                1,
+            )
+            .intersection(area);
+            let style = if is_weekend(*weekday) {
+                self.theme.weekend
+            } else {
+                self.theme.header
+            };
+            Text::styled(name, style)
+                .alignment(Alignment::Right)
+                .render(name_area, buf);
+        }
+
+        let today = Local::now().date_naive();
+        for (date, day_area) in self.day_areas(area) {
+            let mut style = Style::default();
+            if is_weekend(date.weekday()) {
+                style = style.patch(self.theme.weekend);
+            }
+            if date.month() != self.selected_date.month() {
+                style = style.patch(self.theme.adjacent_month);
+            }
+            if self.busy_days.contains(&date) {
+                style = style.add_modifier(Modifier::UNDERLINED);
+            }
+            if date == today {
+                style = style.patch(self.theme.today);
+            }
+            if date == self.selected_date {
+                style = style.patch(self.theme.selection);
+            }
+            // only the number is styled, not the space in front of it
+            Line::from(vec![
+                Span::from(" "),
+                Span::styled(format!("{:>2}", date.day()), style),
+            ])
+            .alignment(Alignment::Right)
+            .render(day_area, buf);
+        }
+    }
+}
+
+/// A list of the events of one day, with their times and locations
+pub struct AgendaWidget<'a> {
+    title: String,
+    /// each event with the color to draw it in
+    events: Vec<(&'a CalendarEvent, EventColor)>,
+    theme: &'a Theme,
+    time_format: TimeFormat,
+    locale: Locale,
+}
+
+impl<'a> AgendaWidget<'a> {
+    pub fn new(
+        title: String,
+        events: Vec<(&'a CalendarEvent, EventColor)>,
+        theme: &'a Theme,
+        time_format: TimeFormat,
+        locale: Locale,
+    ) -> Self {
+        AgendaWidget {
+            title,
+            events,
+            theme,
+            time_format,
+            locale,
+        }
+    }
+}
+
+impl StatefulWidget for AgendaWidget<'_> {
+    type State = ListState;
+
+    fn render(self, area: Rect, buf: &mut Buffer, state: &mut Self::State) {
+        let block = Block::bordered()
+            .border_style(self.theme.border)
+            .title(Line::styled(self.title, self.theme.header));
+        if self.events.is_empty() {
+            Paragraph::new("No events").block(block).render(area, buf);
+            return;
+        }
+
+        let width = area.width.saturating_sub(2) as usize;
+        // there's room for everything about each event, since it has the whole width
+        let items: Vec<Line> = self
+            .events
+            .iter()
+            .flat_map(|(event, color)| {
+                let event_widget = EventWidget::new(event, *color, self.theme)
+                    .time_format(self.time_format, self.locale)
+                    .density(EventDensity::Verbose);
+                Text::from(&event_widget).lines
+            })
+            .map(|line| truncate_line(line, width))
+            .collect();
+
+        let list = List::new(items)
+            .block(block)
+            .highlight_style(self.theme.selection);
+        StatefulWidget::render(list, area, buf, state);
+    }
+}
+
+/// Returns the days shown in the compact month containing `date` that `has_events` says have
+/// events
+pub fn busy_days(
+    date: NaiveDate,
+    week_start: Weekday,
+    has_events: impl Fn(&NaiveDate) -> bool,
+) -> HashSet<NaiveDate> {
+    let first_of_month = date.with_day(1).unwrap_or(date);
+    start_of_week(first_of_month, week_start)
+        .iter_days()
+        .take(weeks_in_month(date, week_start) as usize * 7)
+        .filter(|date| has_events(date))
+        .collect()
+}
@@ -0,0 +1,163 @@
+use chrono::{Locale, NaiveTime, Weekday};
+use directories::ProjectDirs;
+use serde::Deserialize;
+use std::path::PathBuf;
+
+use crate::colors::ColorSupport;
+use crate::error::TalendarError;
+use crate::event_widget::{EventDensity, TimeFormat};
+use crate::login::LoginMethod;
+use crate::quick_add::QuickAddParser;
+use crate::reminders::NotificationMethod;
+use crate::status::StatusConfig;
+use crate::theme::ThemeName;
+
+const CONFIG_FILE_NAME: &str = "config.toml";
+
+/// User configuration, read from `config.toml` in the talendar config directory.
+/// Any field missing from the file falls back to its default value.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The day that each week starts on, e.g. "monday" or "sunday"
+    pub week_start: Weekday,
+
+    /// Show the ISO week number in a gutter to the left of each week
+    pub show_week_numbers: bool,
+
+    /// Locale used for month and weekday names and the order of numeric dates, e.g. "de_DE".
+    /// When unset, the locale is taken from the `LC_ALL`, `LC_TIME` or `LANG` environment variables.
+    pub locale: Option<String>,
+
+    /// The clock event times are shown with: "locale", "12h" or "24h"
+    pub time_format: TimeFormat,
+
+    /// How much of each event is shown in the calendar: "compact" for the start time and title,
+    /// or "verbose" to add the end time and location
+    pub event_density: EventDensity,
+
+    /// The built in theme to use: "dark", "light", "high-contrast" or "no-color". The theme
+    /// without colors is always used when the `NO_COLOR` environment variable is set.
+    pub theme: ThemeName,
+
+    /// How many colors the terminal can show: "auto", "truecolor", "256" or "16"
+    pub color_support: ColorSupport,
+
+    /// Hide events whose invitation we've declined. This can also be toggled while talendar is
+    /// running.
+    pub hide_declined_events: bool,
+
+    /// How to get your attention when a reminder fires: "none", "bell", "osc9" or "osc777"
+    pub notification: NotificationMethod,
+
+    /// How many minutes a snoozed reminder waits before showing again
+    pub snooze_minutes: u32,
+
+    /// When the working day starts and ends, e.g. ["09:00", "17:00"]. Used to find free time.
+    pub working_hours: (NaiveTime, NaiveTime),
+
+    /// The days of the week that are worked. Used to find free time.
+    pub working_days: Vec<Weekday>,
+
+    /// Who parses quick add text: "google" or "local"
+    pub quick_add_parser: QuickAddParser,
+
+    /// How to log in to Google: "browser", "device" to enter a code on another device, or "auto"
+    /// to use a code when there's no display, like over ssh
+    pub login_method: LoginMethod,
+
+    /// The port the browser sends the login back to. Any free port is used when unset.
+    pub login_port: Option<u16>,
+
+    /// How many calendars are synced at once
+    pub sync_concurrency: usize,
+
+    /// Templates for `talendar status`, in a `[status]` table
+    pub status: StatusConfig,
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            week_start: Weekday::Mon,
+            show_week_numbers: false,
+            locale: None,
+            time_format: TimeFormat::Locale,
+            event_density: EventDensity::Compact,
+            theme: ThemeName::Dark,
+            color_support: ColorSupport::Auto,
+            hide_declined_events: false,
+            notification: NotificationMethod::None,
+            snooze_minutes: 5,
+            working_hours: (
+                NaiveTime::from_hms_opt(9, 0, 0).unwrap_or_default(),
+                NaiveTime::from_hms_opt(17, 0, 0).unwrap_or_default(),
+            ),
+            working_days: vec![
+                Weekday::Mon,
+                Weekday::Tue,
+                Weekday::Wed,
+                Weekday::Thu,
+                Weekday::Fri,
+            ],
+            quick_add_parser: QuickAddParser::Google,
+            login_method: LoginMethod::Auto,
+            login_port: None,
+            sync_concurrency: 4,
+            status: StatusConfig::default(),
+        }
+    }
+}
+
+impl Config {
+    /// Load the config file if there is one, otherwise use the default config. Fails if the
+    /// file can't be parsed.
+    pub fn load() -> Result<Self, TalendarError> {
+        let Some(path) = determine_config_path() else {
+            return Ok(Config::default());
+        };
+
+        match std::fs::read_to_string(&path) {
+            Ok(contents) => toml::from_str(&contents).map_err(|err| {
+                TalendarError::Config(format!("Failed to parse {}: {}", path.display(), err))
+            }),
+            // no config file, so just use the defaults
+            Err(_) => Ok(Config::default()),
+        }
+    }
+
+    /// Load the config file, falling back to the default config if it can't be parsed. The
+    /// error is returned as well, so that it can be shown.
+    pub fn load_or_default() -> (Self, Option<TalendarError>) {
+        match Config::load() {
+            Ok(config) => (config, None),
+            Err(err) => (Config::default(), Some(err)),
+        }
+    }
+
+    /// Resolve the configured locale, falling back to the environment and then to POSIX
+    pub fn locale(&self) -> Locale {
+        let from_env = || {
+            ["LC_ALL", "LC_TIME", "LANG"]
+                .iter()
+                .find_map(|var| std::env::var(var).ok().filter(|value| !value.is_empty()))
+        };
+
+        self.locale
+            .clone()
+            .or_else(from_env)
+            .and_then(|name| parse_locale(&name))
+            .unwrap_or(Locale::POSIX)
+    }
+}
+
+/// Parse a locale name such as "de_DE.UTF-8@euro", ignoring the encoding and modifier
+fn parse_locale(name: &str) -> Option<Locale> {
+    let name = name.split(['.', '@']).next()?;
+    Locale::try_from(name).ok()
+}
+
+fn determine_config_path() -> Option<PathBuf> {
+    ProjectDirs::from("", "", "talendar")
+        .map(|project_dir| project_dir.config_dir().join(CONFIG_FILE_NAME))
+}
@@ -0,0 +1,160 @@
+use chrono::{Days, Local, NaiveDate, NaiveDateTime};
+use crossterm::event::KeyEvent;
+use google_calendar3::api::{Event as CalendarEvent, EventDateTime};
+use ratatui::Frame;
+
+use crate::form::{draw_form, Form, FormEvent};
+use crate::theme::Theme;
+
+const FIELD_LABELS: [&str; 4] = ["Title", "Starts", "Ends", "Location"];
+
+const DATE_FORMAT: &str = "%Y-%m-%d";
+const DATE_TIME_FORMAT: &str = "%Y-%m-%d %H:%M";
+
+/// A form for the title, time and location of an existing event. Each field is edited as it is,
+/// so that anything left alone stays the way it was.
+pub struct EditEventForm {
+    form: Form<4>,
+}
+
+impl EditEventForm {
+    /// A form filled in with the details of `event`. All day events show the last day they're
+    /// on as the end, rather than the day after like the api.
+    pub fn new(event: &CalendarEvent) -> Self {
+        let (start, end) = match (event.start.as_ref(), event.end.as_ref()) {
+            (
+                Some(EventDateTime {
+                    date_time: Some(start),
+                    ..
+                }),
+                Some(EventDateTime {
+                    date_time: Some(end),
+                    ..
+                }),
+            ) => (
+                start
+                    .with_timezone(&Local)
+                    .format(DATE_TIME_FORMAT)
+                    .to_string(),
+                end.with_timezone(&Local)
+                    .format(DATE_TIME_FORMAT)
+                    .to_string(),
+            ),
+            (
+                Some(EventDateTime {
+                    date: Some(start), ..
+                }),
+                Some(EventDateTime {
+                    date: Some(end), ..
+                }),
+            ) => (
+                start.format(DATE_FORMAT).to_string(),
+                end.pred_opt()
+                    .unwrap_or(*end)
+                    .max(*start)
+                    .format(DATE_FORMAT)
+                    .to_string(),
+            ),
+            _ => (String::new(), String::new()),
+        };
+
+        let values = [
+            event.summary.clone().unwrap_or_default(),
+            start,
+            end,
+            event.location.clone().unwrap_or_default(),
+        ];
+        EditEventForm {
+            form: Form::new("Edit event", "save", FIELD_LABELS, values),
+        }
+    }
+
+    pub fn on_key_event(&mut self, key: KeyEvent) -> FormEvent {
+        self.form.on_key_event(key)
+    }
+
+    /// Parses the form into the changes to patch the event with, or describes the first field
+    /// that doesn't make sense. An empty location is sent as an empty string, which clears it.
+    pub fn to_changes(&self) -> Result<CalendarEvent, String> {
+        let [summary, start, end, location] = &self.form.values;
+
+        let summary = summary.trim();
+        if summary.is_empty() {
+            return Err(String::from("The event needs a title"));
+        }
+
+        let (start, end) = match (
+            parse_date_or_date_time(start)?,
+            parse_date_or_date_time(end)?,
+        ) {
+            (DateOrDateTime::Date(start), DateOrDateTime::Date(end)) => {
+                if end < start {
+                    return Err(String::from("The event ends before it starts"));
+                }
+                (
+                    EventDateTime {
+                        date: Some(start),
+                        ..Default::default()
+                    },
+                    EventDateTime {
+                        date: end.checked_add_days(Days::new(1)),
+                        ..Default::default()
+                    },
+                )
+            }
+            (DateOrDateTime::DateTime(start), DateOrDateTime::DateTime(end)) => {
+                let (Some(start), Some(end)) = (
+                    start.and_local_timezone(Local).earliest(),
+                    end.and_local_timezone(Local).earliest(),
+                ) else {
+                    return Err(String::from("That time doesn't exist here"));
+                };
+                if end <= start {
+                    return Err(String::from("The event ends before it starts"));
+                }
+                (
+                    EventDateTime {
+                        date_time: Some(start.to_utc()),
+                        ..Default::default()
+                    },
+                    EventDateTime {
+                        date_time: Some(end.to_utc()),
+                        ..Default::default()
+                    },
+                )
+            }
+            _ => {
+                return Err(String::from(
+                    "The start and end need to both have times, or both be all day",
+                ))
+            }
+        };
+
+        Ok(CalendarEvent {
+            summary: Some(summary.to_string()),
+            location: Some(location.trim().to_string()),
+            start: Some(start),
+            end: Some(end),
+            ..Default::default()
+        })
+    }
+}
+
+/// A day, for all day events, or a time
+enum DateOrDateTime {
+    Date(NaiveDate),
+    DateTime(NaiveDateTime),
+}
+
+/// Parses "2025-01-31" or "2025-01-31 13:00"
+fn parse_date_or_date_time(value: &str) -> Result<DateOrDateTime, String> {
+    let value = value.trim();
+    NaiveDateTime::parse_from_str(value, DATE_TIME_FORMAT)
+        .map(DateOrDateTime::DateTime)
+        .or_else(|_| NaiveDate::parse_from_str(value, DATE_FORMAT).map(DateOrDateTime::Date))
+        .map_err(|_| format!("\"{value}\" isn't a date like 2025-01-31 or 2025-01-31 13:00"))
+}
+
+pub fn draw_edit_event_form(frame: &mut Frame, form: &EditEventForm, theme: &Theme) {
+    draw_form(frame, &form.form, theme);
+}
@@ -0,0 +1,65 @@
+use std::{error::Error, io, path::PathBuf};
+
+use google_calendar3::yup_oauth2;
+use thiserror::Error;
+
+/// Everything that can go wrong talking to Google Calendar or keeping its data on disk, grouped
+/// by what the user can do about it
+#[derive(Debug, Error)]
+pub enum TalendarError {
+    /// we couldn't get a token, e.g. because the login was revoked
+    #[error("Couldn't log in to Google: {0}")]
+    Auth(Box<dyn Error + Send + Sync>),
+
+    /// Google couldn't be reached, probably because we're offline
+    #[error("Couldn't reach Google. Check your internet connection. ({0})")]
+    Network(Box<dyn Error + Send + Sync>),
+
+    /// Google was reached but didn't do what we asked
+    #[error("Google Calendar returned an error: {0}")]
+    Api(Box<google_calendar3::Error>),
+
+    /// the cache, or the directory it's kept in, couldn't be read or written
+    #[error("Couldn't write {}: {source}", path.display())]
+    Cache {
+        path: PathBuf,
+        #[source]
+        source: io::Error,
+    },
+
+    /// a file we need to be set up is missing or wrong, like config.toml or clientsecret.json
+    #[error("{0}")]
+    Config(String),
+}
+
+impl From<google_calendar3::Error> for TalendarError {
+    fn from(err: google_calendar3::Error) -> Self {
+        match err {
+            google_calendar3::Error::HttpError(_) | google_calendar3::Error::Io(_) => {
+                TalendarError::Network(Box::new(err))
+            }
+            google_calendar3::Error::MissingToken(err) => TalendarError::from_token_error(err),
+            err => TalendarError::Api(Box::new(err)),
+        }
+    }
+}
+
+impl TalendarError {
+    /// Sorts an error from getting a token into whether Google couldn't be reached or the login
+    /// itself failed
+    fn from_token_error(err: Box<dyn Error + Send + Sync>) -> Self {
+        if is_network_error(err.as_ref()) {
+            TalendarError::Network(err)
+        } else {
+            TalendarError::Auth(err)
+        }
+    }
+}
+
+/// Returns `true` if getting a token failed because Google couldn't be reached
+pub fn is_network_error(err: &(dyn Error + Send + Sync + 'static)) -> bool {
+    matches!(
+        err.downcast_ref::<yup_oauth2::Error>(),
+        Some(yup_oauth2::Error::HttpError(_) | yup_oauth2::Error::HttpClientError(_))
+    )
+}
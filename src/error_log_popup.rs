@@ -0,0 +1,59 @@
+use chrono::{DateTime, Local, Locale};
+use ratatui::{
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::theme::Theme;
+use crate::utils::{popup_area, time_string};
+
+/// An error that was shown to the user, kept so that it can be looked at again after the banner
+/// has gone
+pub struct ErrorLogEntry {
+    pub time: DateTime<Local>,
+    pub message: String,
+}
+
+/// Draws a popup listing the errors in `entries`, newest first, scrolled down by `scroll` lines
+pub fn draw_error_log_popup(
+    frame: &mut Frame,
+    entries: &[ErrorLogEntry],
+    theme: &Theme,
+    locale: Locale,
+    scroll: u16,
+) {
+    let block = theme
+        .popup_block()
+        .title("Errors")
+        .title_bottom("[j/k] scroll  [Esc] close");
+    let area = popup_area(frame.area(), 80, 60);
+    frame.render_widget(Clear, area);
+
+    let lines: Vec<Line> = if entries.is_empty() {
+        vec![Line::styled(
+            "No errors since talendar started",
+            theme.secondary,
+        )]
+    } else {
+        entries
+            .iter()
+            .rev()
+            .map(|entry| {
+                Line::from(vec![
+                    Span::styled(
+                        format!("{}  ", time_string(&entry.time, locale)),
+                        theme.secondary,
+                    ),
+                    Span::from(entry.message.as_str()),
+                ])
+            })
+            .collect()
+    };
+
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0));
+    frame.render_widget(paragraph, area);
+}
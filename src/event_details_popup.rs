@@ -0,0 +1,276 @@
+use chrono::{Days, Local, Locale};
+use google_calendar3::api::{CalendarListEntry, Event as CalendarEvent, EventReminder};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+    Frame,
+};
+
+use crate::google_cal_backend::CalendarEventExt;
+use crate::recurrence::describe_recurrence;
+use crate::theme::Theme;
+use crate::utils::{date_string, duration_string, popup_area, time_string};
+
+const DEFAULT_TITLE: &str = "No title";
+
+/// Draws a popup with everything we know about `event`. `recurring_event` is the master event of
+/// its series, if it has one. The contents are wrapped to the width of the popup, and scrolled
+/// down by `scroll` lines.
+pub fn draw_event_details_popup(
+    frame: &mut Frame,
+    event: &CalendarEvent,
+    recurring_event: Option<&CalendarEvent>,
+    calendar: Option<&CalendarListEntry>,
+    theme: &Theme,
+    locale: Locale,
+    scroll: u16,
+) {
+    let title = event.summary.clone().unwrap_or(String::from(DEFAULT_TITLE));
+    // we can only respond to events we've been invited to
+    let controls = if event.self_response_status().is_some() {
+        "[a]ccept  [d]ecline  [t]entative (shift to add a comment)  [e]dit  [X] delete  [j/k] scroll  [Esc] close"
+    } else {
+        "[e]dit  [X] delete  [j/k] scroll  [Esc] close"
+    };
+    let block = theme.popup_block().title(title).title_bottom(controls);
+    let area = popup_area(frame.area(), 80, 80);
+    frame.render_widget(Clear, area); // this clears out the background
+
+    let lines = event_details_lines(event, recurring_event, calendar, locale);
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false })
+        .scroll((scroll, 0));
+
+    frame.render_widget(paragraph, area);
+}
+
+/// Builds the lines of the details popup. Sections the event has no data for are left out.
+fn event_details_lines<'a>(
+    event: &'a CalendarEvent,
+    recurring_event: Option<&CalendarEvent>,
+    calendar: Option<&'a CalendarListEntry>,
+    locale: Locale,
+) -> Vec<Line<'a>> {
+    let mut lines = vec![
+        labelled_line("When", time_range_string(event, locale)),
+        labelled_line("Calendar", calendar_name(calendar)),
+    ];
+
+    if let Some(location) = &event.location {
+        lines.push(labelled_line("Where", location.clone()));
+    }
+
+    if let Some(organizer) = &event.organizer {
+        let name = person_string(organizer.display_name.as_ref(), organizer.email.as_ref());
+        lines.push(labelled_line("Organizer", name));
+    }
+
+    if let Some(link) = conference_link(event) {
+        lines.push(labelled_line("Join", link.to_string()));
+    }
+
+    if let Some(recurrence) = recurrence_string(event, recurring_event, locale) {
+        lines.push(labelled_line("Repeats", recurrence));
+    }
+
+    let reminders = reminder_strings(event, calendar);
+    if !reminders.is_empty() {
+        lines.push(labelled_line("Reminders", reminders.join(", ")));
+    }
+
+    if let Some(attendees) = &event.attendees {
+        lines.push(Line::default());
+        lines.push(heading_line(format!("Guests ({})", attendees.len())));
+        for attendee in attendees {
+            let mut name = person_string(attendee.display_name.as_ref(), attendee.email.as_ref());
+            if attendee.organizer == Some(true) {
+                name += " (organizer)";
+            }
+            if attendee.optional == Some(true) {
+                name += " (optional)";
+            }
+            lines.push(Line::from(format!(
+                "  {} {}",
+                response_status_symbol(attendee.response_status.as_deref()),
+                name
+            )));
+        }
+    }
+
+    if let Some(attachments) = &event.attachments {
+        lines.push(Line::default());
+        lines.push(heading_line(String::from("Attachments")));
+        for attachment in attachments {
+            let title = attachment.title.as_deref().unwrap_or("Untitled attachment");
+            let url = attachment.file_url.as_deref().unwrap_or_default();
+            lines.push(Line::from(format!("  {} {}", title, url)));
+        }
+    }
+
+    if let Some(description) = &event.description {
+        lines.push(Line::default());
+        lines.push(heading_line(String::from("Description")));
+        lines.extend(description.lines().map(Line::from));
+    }
+
+    lines
+}
+
+fn labelled_line<'a>(label: &'a str, value: String) -> Line<'a> {
+    Line::from(vec![
+        Span::styled(
+            format!("{label}: "),
+            Style::default().add_modifier(Modifier::BOLD),
+        ),
+        Span::from(value),
+    ])
+}
+
+fn heading_line<'a>(heading: String) -> Line<'a> {
+    Line::styled(heading, Style::default().add_modifier(Modifier::BOLD))
+}
+
+/// Returns the start and end of the event and how long it lasts, e.g.
+/// "01/31/25 09:00 AM - 10:30 AM (1h 30m)"
+fn time_range_string(event: &CalendarEvent, locale: Locale) -> String {
+    let (Some(start), Some(end)) = (&event.start, &event.end) else {
+        return String::from("No Start Time");
+    };
+
+    if let (Some(start), Some(end)) = (start.date_time, end.date_time) {
+        let (start, end) = (start.with_timezone(&Local), end.with_timezone(&Local));
+        let end_string = if start.date_naive() == end.date_naive() {
+            time_string(&end, locale)
+        } else {
+            format!(
+                "{} {}",
+                date_string(end.date_naive(), locale),
+                time_string(&end, locale)
+            )
+        };
+        return format!(
+            "{} {} - {} ({})",
+            date_string(start.date_naive(), locale),
+            time_string(&start, locale),
+            end_string,
+            duration_string(end - start)
+        );
+    }
+
+    if let (Some(start), Some(end)) = (start.date, end.date) {
+        // the end date of an all day event is exclusive
+        let last_day = end.checked_sub_days(Days::new(1)).unwrap_or(end).max(start);
+        if last_day == start {
+            return format!("{} (all day)", date_string(start, locale));
+        }
+        return format!(
+            "{} - {} ({} days)",
+            date_string(start, locale),
+            date_string(last_day, locale),
+            (end - start).num_days()
+        );
+    }
+
+    String::from("No Start Time")
+}
+
+fn calendar_name(calendar: Option<&CalendarListEntry>) -> String {
+    calendar
+        .and_then(|calendar| {
+            calendar
+                .summary_override
+                .as_ref()
+                .or(calendar.summary.as_ref())
+        })
+        .cloned()
+        .unwrap_or(String::from("Unknown calendar"))
+}
+
+/// Formats a person as "Name <email>", or whichever of the two we have
+fn person_string(display_name: Option<&String>, email: Option<&String>) -> String {
+    match (display_name, email) {
+        (Some(name), Some(email)) => format!("{name} <{email}>"),
+        (Some(name), None) => name.clone(),
+        (None, Some(email)) => email.clone(),
+        (None, None) => String::from("Unknown"),
+    }
+}
+
+fn response_status_symbol(response_status: Option<&str>) -> &'static str {
+    match response_status {
+        Some("accepted") => "✓",
+        Some("declined") => "✗",
+        Some("tentative") => "?",
+        _ => "·",
+    }
+}
+
+/// Returns the link to join the event's video call, if it has one
+fn conference_link(event: &CalendarEvent) -> Option<&str> {
+    let video_entry_point = event
+        .conference_data
+        .as_ref()
+        .and_then(|conference_data| conference_data.entry_points.as_ref())
+        .and_then(|entry_points| {
+            entry_points
+                .iter()
+                .find(|entry_point| entry_point.entry_point_type.as_deref() == Some("video"))
+        })
+        .and_then(|entry_point| entry_point.uri.as_deref());
+
+    video_entry_point.or(event.hangout_link.as_deref())
+}
+
+/// Describes how the event repeats. Expanded instances of a recurring event don't carry the rules
+/// themselves, so they come from the master event of the series. If we don't have that, we can
+/// only say that the event repeats.
+fn recurrence_string(
+    event: &CalendarEvent,
+    recurring_event: Option<&CalendarEvent>,
+    locale: Locale,
+) -> Option<String> {
+    let rules = event
+        .recurrence
+        .as_ref()
+        .or(recurring_event.and_then(|recurring_event| recurring_event.recurrence.as_ref()));
+    match (rules, &event.recurring_event_id) {
+        (Some(rules), _) => describe_recurrence(rules, locale),
+        (None, Some(_)) => Some(String::from("Part of a recurring series")),
+        (None, None) => None,
+    }
+}
+
+/// Returns a description of each of the event's reminders, e.g. "10 minutes before (popup)".
+/// Events using the default reminders get the defaults of their calendar.
+fn reminder_strings(event: &CalendarEvent, calendar: Option<&CalendarListEntry>) -> Vec<String> {
+    let Some(reminders) = &event.reminders else {
+        return Vec::new();
+    };
+
+    let reminders = if reminders.use_default == Some(true) {
+        calendar.and_then(|calendar| calendar.default_reminders.as_ref())
+    } else {
+        reminders.overrides.as_ref()
+    };
+
+    reminders
+        .map(|reminders| reminders.iter().map(reminder_string).collect())
+        .unwrap_or_default()
+}
+
+fn reminder_string(reminder: &EventReminder) -> String {
+    let minutes = reminder.minutes.unwrap_or_default();
+    let before = match minutes {
+        minutes if minutes > 0 && minutes % (60 * 24) == 0 => {
+            format!("{} days", minutes / (60 * 24))
+        }
+        minutes if minutes > 0 && minutes % 60 == 0 => format!("{} hours", minutes / 60),
+        minutes => format!("{minutes} minutes"),
+    };
+    match &reminder.method {
+        Some(method) => format!("{before} before ({method})"),
+        None => format!("{before} before"),
+    }
+}
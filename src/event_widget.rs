@@ -1,61 +1,244 @@
 // Define an event widget
-use google_calendar3::api::{Colors, Event as CalendarEvent};
+use chrono::{DateTime, Local, Locale, Timelike};
+use google_calendar3::api::Event as CalendarEvent;
 use ratatui::{
-    buffer::Buffer, 
-    layout::Rect, 
-    text::Span, 
-    style::Style,
+    buffer::Buffer,
+    layout::Rect,
     prelude::{Line, Text},
-    widgets::Widget};
+    style::{Color, Modifier, Style},
+    text::Span,
+    widgets::Widget,
+};
+use serde::Deserialize;
 
-use crate::utils::get_event_color;
+use crate::colors::EventColor;
+use crate::google_cal_backend::CalendarEventExt;
+use crate::theme::Theme;
+use crate::utils::uses_24_hour_clock;
 
-const DEFAULT_TITLE : &str = "No title";
-const DEFAULT_COLOR : &str = "#FFFFFF";
+const DEFAULT_TITLE: &str = "No title";
 
+/// Which clock the times of events are shown with
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+pub enum TimeFormat {
+    /// whichever clock the locale uses
+    #[default]
+    #[serde(rename = "locale")]
+    Locale,
+    /// e.g. 1:30pm
+    #[serde(rename = "12h")]
+    TwelveHour,
+    /// e.g. 13:30
+    #[serde(rename = "24h")]
+    TwentyFourHour,
+}
+
+impl TimeFormat {
+    /// Formats the time of `date_time`. 12 hour times are kept short, e.g. "9am" or "1:30pm"
+    pub fn format(&self, date_time: DateTime<Local>, locale: Locale) -> String {
+        let twenty_four_hour = match self {
+            TimeFormat::Locale => uses_24_hour_clock(locale),
+            TimeFormat::TwelveHour => false,
+            TimeFormat::TwentyFourHour => true,
+        };
+        if twenty_four_hour {
+            return date_time.format_localized("%H:%M", locale).to_string();
+        }
+
+        let format = match date_time.minute() {
+            0 => "%-I",
+            _ => "%-I:%M",
+        };
+        // many locales, like de_DE, don't have am and pm of their own
+        let marker = match date_time.format_localized("%P", locale).to_string() {
+            marker if !marker.is_empty() => marker,
+            _ if date_time.hour() < 12 => String::from("am"),
+            _ => String::from("pm"),
+        };
+        format!("{}{marker}", date_time.format_localized(format, locale))
+    }
+}
+
+/// How much of each event is shown in the calendar
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum EventDensity {
+    /// the start time and title
+    #[default]
+    Compact,
+    /// the start and end times, the title and the location
+    Verbose,
+}
 
 pub struct EventWidget<'a> {
     event: &'a CalendarEvent,
-    colors : &'a Colors,
+    color: EventColor,
+    theme: &'a Theme,
+    time_format: TimeFormat,
+    density: EventDensity,
+    locale: Locale,
+    /// events that ended before this are greyed out
+    now: DateTime<Local>,
 }
 
 impl<'a> Widget for EventWidget<'a> {
     fn render(self, area: Rect, buf: &mut Buffer) {
-        // TODO: This does not appear to work for some reason
-        let color = get_event_color(self.event, self.colors);
-
-        let default_title = String::from("No title");
-        let title = self.event.summary.as_ref().unwrap_or(&default_title);
-
-        let line = Line::from(vec![
-            Span::styled("• ", Style::default().fg(color)),
-            Span::styled(title, Style::default())
-        ]);
-        // buf.set_line(0, 0, &line, area.width);
-        line.render(area, buf);
+        Text::from(&self).render(area, buf);
     }
 }
 
 // We want this so that we can populate the List widget with EventWidgets
-impl<'a> Into<Text<'a>> for &EventWidget<'a> {
-    fn into(self) -> Text<'a> {
-        let color_hex = String::from( self.event.color_id.as_ref().unwrap_or(&String::from(DEFAULT_COLOR)) );
-        let color = serde_json::from_str(&color_hex).unwrap_or_default();
-        let title = String::from(self.event.summary.as_ref().unwrap_or(&String::from(DEFAULT_TITLE)));
-
-        let line = Line::from(vec![
-            Span::styled("• ", Style::default().fg(color)),
-            Span::styled(title, Style::default())
-        ]
+impl<'a> From<&EventWidget<'a>> for Text<'a> {
+    fn from(event_widget: &EventWidget<'a>) -> Text<'a> {
+        let event = event_widget.event;
+        let is_past = event.is_past(event_widget.now);
+        let theme = event_widget.theme;
+        let color = event_widget.color;
+        let title = String::from(
+            event
+                .summary
+                .as_ref()
+                .unwrap_or(&String::from(DEFAULT_TITLE)),
         );
 
-        Text::from(line)
+        // declined events are struck through, and events we might not go to are dimmed.
+        // Invitations we haven't responded to yet get a hollow bullet and an italic title
+        let mut title_style = Style::default();
+        if event.is_declined() {
+            title_style = title_style.add_modifier(Modifier::CROSSED_OUT | Modifier::DIM);
+        } else if event.is_tentative() {
+            title_style = title_style.add_modifier(Modifier::DIM);
+        } else if event.is_pending_invite() {
+            title_style = title_style.add_modifier(Modifier::ITALIC);
+        }
+        let bullet = if event.is_pending_invite() {
+            "○ "
+        } else {
+            "• "
+        };
+
+        let location = match (event_widget.density, &event.location) {
+            (EventDensity::Verbose, Some(location)) => Some(format!(" @ {location}")),
+            _ => None,
+        };
+
+        // all day events are a bar in the event's color, so they stand out from timed events
+        if event.is_all_day() {
+            let mut spans = vec![Span::from(" "), Span::styled(title, title_style)];
+            spans.extend(location.map(Span::from));
+            // past bars are drawn in the past event color instead of their own
+            let (bar_color, bar_text) = match theme.past_event.fg {
+                Some(past_color) if is_past => (past_color, Color::Black),
+                _ => (color.color, color.text),
+            };
+            let mut bar_style = theme.all_day_event.bg(bar_color).fg(bar_text);
+            if is_past {
+                bar_style = bar_style.add_modifier(theme.past_event.add_modifier);
+            }
+            return Text::from(Line::from(spans).style(bar_style));
+        }
+
+        let past_style = if is_past {
+            theme.past_event
+        } else {
+            Style::default()
+        };
+        let mut spans = vec![Span::styled(
+            bullet,
+            Style::default().fg(color.color).patch(past_style),
+        )];
+        spans.extend(
+            event_widget
+                .time_string()
+                .map(|time| Span::styled(time + " ", past_style)),
+        );
+        spans.push(Span::styled(title, past_style.patch(title_style)));
+        spans.extend(
+            location.map(|location| Span::styled(location, past_style.add_modifier(Modifier::DIM))),
+        );
+
+        Text::from(Line::from(spans))
     }
 }
 
-
 impl<'a> EventWidget<'a> {
-    pub fn new(event: & 'a CalendarEvent, colors: &'a Colors) -> Self {
-        EventWidget { event , colors }
+    pub fn new(event: &'a CalendarEvent, color: EventColor, theme: &'a Theme) -> Self {
+        EventWidget {
+            event,
+            color,
+            theme,
+            time_format: TimeFormat::default(),
+            density: EventDensity::default(),
+            locale: Locale::POSIX,
+            now: Local::now(),
+        }
+    }
+
+    /// Sets the clock and locale that the event's times are shown with
+    pub fn time_format(mut self, time_format: TimeFormat, locale: Locale) -> Self {
+        self.time_format = time_format;
+        self.locale = locale;
+        self
+    }
+
+    pub fn density(mut self, density: EventDensity) -> Self {
+        self.density = density;
+        self
+    }
+
+    /// Returns the start time, or the start and end times if the density is verbose. All day
+    /// events don't have one.
+    fn time_string(&self) -> Option<String> {
+        if self.event.is_all_day() {
+            return None;
+        }
+        let (start, end) = self.event.get_local_bounds()?;
+        let start_string = self.time_format.format(start, self.locale);
+        match self.density {
+            EventDensity::Compact => Some(start_string),
+            EventDensity::Verbose => Some(format!(
+                "{start_string}-{}",
+                self.time_format.format(end, self.locale)
+            )),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    fn at(hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2025, 1, 31, hour, minute, 0)
+            .unwrap()
+    }
+
+    #[test]
+    fn twelve_hour_times_are_kept_short() {
+        let format = |date_time| TimeFormat::TwelveHour.format(date_time, Locale::en_US);
+        assert_eq!(format(at(9, 0)), "9am");
+        assert_eq!(format(at(13, 30)), "1:30pm");
+        assert_eq!(format(at(0, 5)), "12:05am");
+    }
+
+    #[test]
+    fn twelve_hour_times_have_am_and_pm_in_locales_without_them() {
+        let format = |date_time| TimeFormat::TwelveHour.format(date_time, Locale::de_DE);
+        assert_eq!(format(at(9, 0)), "9am");
+        assert_eq!(format(at(13, 30)), "1:30pm");
+    }
+
+    #[test]
+    fn twenty_four_hour_times() {
+        let format = |date_time| TimeFormat::TwentyFourHour.format(date_time, Locale::en_US);
+        assert_eq!(format(at(9, 0)), "09:00");
+        assert_eq!(format(at(13, 30)), "13:30");
+        assert_eq!(
+            TimeFormat::Locale.format(at(13, 30), Locale::de_DE),
+            "13:30"
+        );
     }
 }
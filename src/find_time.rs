@@ -0,0 +1,456 @@
+use chrono::{
+    DateTime, Datelike, Days, Local, Locale, NaiveDate, NaiveTime, TimeDelta, Timelike, Utc,
+    Weekday,
+};
+use crossterm::event::KeyEvent;
+use ratatui::{
+    layout::{Constraint, Layout},
+    widgets::{Clear, List, ListState, Paragraph},
+    Frame,
+};
+
+use crate::config::Config;
+use crate::form::{draw_form, Form, FormEvent};
+use crate::theme::Theme;
+use crate::utils::{date_string, popup_area, time_string, weekday_short_name};
+
+/// Slots are only offered at multiples of this many minutes past the hour
+const SLOT_GRANULARITY_MINUTES: u32 = 15;
+
+/// Where to find out when the calendars are busy
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BusySource {
+    /// ask the FreeBusy API, which works for anyone's calendar we're allowed to see
+    Google,
+    /// use the events in the cache, which only works for calendars we sync
+    Local,
+}
+
+/// Everything needed to look for a free slot
+#[derive(Debug, Clone)]
+pub struct FindTimeRequest {
+    pub duration: TimeDelta,
+    pub first_day: NaiveDate,
+    pub days: u32,
+    pub working_hours: (NaiveTime, NaiveTime),
+    pub working_days: Vec<Weekday>,
+    /// calendar ids, calendar names or email addresses. Empty means all of our calendars.
+    pub calendars: Vec<String>,
+    pub source: BusySource,
+}
+
+impl FindTimeRequest {
+    pub fn last_day(&self) -> NaiveDate {
+        self.first_day + Days::new(self.days.saturating_sub(1).into())
+    }
+
+    /// Returns the start of the first day and the end of the last day
+    pub fn time_bounds(&self) -> Option<(DateTime<Utc>, DateTime<Utc>)> {
+        let local_midnight = |date: NaiveDate| {
+            date.and_hms_opt(0, 0, 0)?
+                .and_local_timezone(Local)
+                .earliest()
+        };
+        let start = local_midnight(self.first_day)?;
+        let end = local_midnight(self.last_day().succ_opt()?)?;
+        Some((start.to_utc(), end.to_utc()))
+    }
+}
+
+/// A gap in everyone's calendars that is long enough for the meeting
+#[derive(Debug, Clone)]
+pub struct FreeSlot {
+    pub start: DateTime<Local>,
+    pub end: DateTime<Local>,
+    /// when the gap the slot is in ends
+    pub free_until: DateTime<Local>,
+}
+
+/// Returns the earliest slot in each gap between `busy` periods that fits the request. Only
+/// working hours on working days, and only times after `now`, are considered.
+pub fn find_free_slots(
+    request: &FindTimeRequest,
+    busy: &[(DateTime<Local>, DateTime<Local>)],
+    now: DateTime<Local>,
+) -> Vec<FreeSlot> {
+    let mut busy = busy.to_vec();
+    busy.sort_by_key(|(start, _)| *start);
+
+    let mut slots = Vec::new();
+    let (day_start, day_end) = request.working_hours;
+
+    for day in request.first_day.iter_days().take(request.days as usize) {
+        if !request.working_days.contains(&day.weekday()) {
+            continue;
+        }
+        let (Some(window_start), Some(window_end)) = (
+            day.and_time(day_start).and_local_timezone(Local).earliest(),
+            day.and_time(day_end).and_local_timezone(Local).earliest(),
+        ) else {
+            continue;
+        };
+
+        let mut cursor = round_up(window_start.max(now));
+        for (busy_start, busy_end) in &busy {
+            if *busy_end <= cursor || *busy_start >= window_end {
+                continue;
+            }
+            if *busy_start - cursor >= request.duration {
+                slots.push(FreeSlot {
+                    start: cursor,
+                    end: cursor + request.duration,
+                    free_until: *busy_start,
+                });
+            }
+            cursor = round_up(cursor.max(*busy_end));
+        }
+        if window_end - cursor >= request.duration {
+            slots.push(FreeSlot {
+                start: cursor,
+                end: cursor + request.duration,
+                free_until: window_end,
+            });
+        }
+    }
+
+    slots
+}
+
+/// Rounds `date_time` up to the next slot boundary
+fn round_up(date_time: DateTime<Local>) -> DateTime<Local> {
+    let date_time = date_time
+        .with_second(0)
+        .and_then(|date_time| date_time.with_nanosecond(0))
+        .unwrap_or(date_time);
+    let past_boundary = date_time.minute() % SLOT_GRANULARITY_MINUTES;
+    if past_boundary == 0 {
+        date_time
+    } else {
+        date_time + TimeDelta::minutes((SLOT_GRANULARITY_MINUTES - past_boundary).into())
+    }
+}
+
+/////////////////////////
+//                     //
+// Find time form      //
+//                     //
+/////////////////////////
+
+const FIELD_LABELS: [&str; 6] = [
+    "Duration (minutes)",
+    "From (YYYY-MM-DD)",
+    "Days",
+    "Working hours",
+    "Calendars or emails",
+    "Source (google/local)",
+];
+
+/// A form for the parameters of a [`FindTimeRequest`]
+pub struct FindTimeForm {
+    form: Form<6>,
+}
+
+impl FindTimeForm {
+    pub fn new(config: &Config, today: NaiveDate) -> Self {
+        let (day_start, day_end) = config.working_hours;
+        let values = [
+            String::from("30"),
+            today.format("%Y-%m-%d").to_string(),
+            String::from("5"),
+            format!("{}-{}", day_start.format("%H:%M"), day_end.format("%H:%M")),
+            String::new(),
+            String::from("google"),
+        ];
+        FindTimeForm {
+            form: Form::new("Find a time", "search", FIELD_LABELS, values),
+        }
+    }
+
+    pub fn on_key_event(&mut self, key: KeyEvent) -> FormEvent {
+        self.form.on_key_event(key)
+    }
+
+    /// Parses the form into a request, or describes the first field that doesn't make sense
+    pub fn to_request(&self, working_days: &[Weekday]) -> Result<FindTimeRequest, String> {
+        let [duration, first_day, days, working_hours, calendars, source] = &self.form.values;
+
+        let duration = duration
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|minutes| *minutes > 0)
+            .ok_or(format!("\"{duration}\" isn't a number of minutes"))?;
+
+        let first_day = NaiveDate::parse_from_str(first_day.trim(), "%Y-%m-%d")
+            .map_err(|_| format!("\"{first_day}\" isn't a date like 2025-01-31"))?;
+
+        let days = days
+            .trim()
+            .parse::<u32>()
+            .ok()
+            .filter(|days| *days > 0)
+            .ok_or(format!("\"{days}\" isn't a number of days"))?;
+
+        let working_hours = parse_time_range(working_hours).ok_or(format!(
+            "\"{working_hours}\" isn't a time range like 09:00-17:00"
+        ))?;
+
+        let calendars = calendars
+            .split(',')
+            .map(str::trim)
+            .filter(|calendar| !calendar.is_empty())
+            .map(String::from)
+            .collect();
+
+        let source = match source.trim().to_lowercase().as_str() {
+            "google" => BusySource::Google,
+            "local" => BusySource::Local,
+            _ => return Err(format!("\"{source}\" isn't \"google\" or \"local\"")),
+        };
+
+        Ok(FindTimeRequest {
+            duration: TimeDelta::minutes(duration.into()),
+            first_day,
+            days,
+            working_hours,
+            working_days: working_days.to_vec(),
+            calendars,
+            source,
+        })
+    }
+}
+
+/// Parses a range of times like "09:00-17:00"
+fn parse_time_range(range: &str) -> Option<(NaiveTime, NaiveTime)> {
+    let (start, end) = range.split_once('-')?;
+    let start = start.trim().parse::<NaiveTime>().ok()?;
+    let end = end.trim().parse::<NaiveTime>().ok()?;
+    (start < end).then_some((start, end))
+}
+
+pub fn draw_find_time_form(frame: &mut Frame, form: &FindTimeForm, theme: &Theme) {
+    draw_form(frame, &form.form, theme);
+}
+
+/////////////////////////
+//                     //
+// Find time results   //
+//                     //
+/////////////////////////
+
+/// The free slots that were found, one of which can be picked to create an event
+pub struct FindTimeResults {
+    pub request: FindTimeRequest,
+    pub slots: Vec<FreeSlot>,
+    /// calendars Google couldn't check, so the slots may not be free in them
+    pub unchecked: Vec<String>,
+    pub list_state: ListState,
+}
+
+impl FindTimeResults {
+    pub fn new(request: FindTimeRequest, slots: Vec<FreeSlot>, unchecked: Vec<String>) -> Self {
+        let list_state = ListState::default().with_selected((!slots.is_empty()).then_some(0));
+        FindTimeResults {
+            request,
+            slots,
+            unchecked,
+            list_state,
+        }
+    }
+
+    pub fn selected_slot(&self) -> Option<&FreeSlot> {
+        self.slots.get(self.list_state.selected()?)
+    }
+}
+
+pub fn draw_find_time_results(
+    frame: &mut Frame,
+    results: &mut FindTimeResults,
+    theme: &Theme,
+    locale: Locale,
+) {
+    let area = popup_area(frame.area(), 60, 60);
+    frame.render_widget(Clear, area); // this clears out the background
+
+    let title = format!(
+        "Free slots between {} and {}",
+        date_string(results.request.first_day, locale),
+        date_string(results.request.last_day(), locale)
+    );
+    let block = theme
+        .popup_block()
+        .title(title)
+        .title_bottom("[j/k] move  [Enter] create event  [Esc] close");
+    let inner_area = block.inner(area);
+    frame.render_widget(block, area);
+
+    // a warning above the slots about the calendars that weren't checked
+    let warning_height = u16::from(!results.unchecked.is_empty());
+    let [warning_area, area] =
+        Layout::vertical([Constraint::Length(warning_height), Constraint::Fill(1)])
+            .areas(inner_area);
+    let warning = format!("Couldn't check {}", results.unchecked.join(", "));
+    frame.render_widget(Paragraph::new(warning).style(theme.error), warning_area);
+
+    if results.slots.is_empty() {
+        frame.render_widget(Paragraph::new("No free slots found"), area);
+        return;
+    }
+
+    let items: Vec<String> = results
+        .slots
+        .iter()
+        .map(|slot| {
+            format!(
+                "{} {} {} - {} (free until {})",
+                weekday_short_name(slot.start.weekday(), locale),
+                date_string(slot.start.date_naive(), locale),
+                time_string(&slot.start, locale),
+                time_string(&slot.end, locale),
+                time_string(&slot.free_until, locale),
+            )
+        })
+        .collect();
+
+    let list = List::new(items).highlight_style(theme.selection);
+    frame.render_stateful_widget(list, area, &mut results.list_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    const WORKING_DAYS: [Weekday; 5] = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+    ];
+
+    /// Looks for `minutes` between 9 and 5 on weekdays, from Monday 27 January 2025
+    fn request(minutes: i64, days: u32) -> FindTimeRequest {
+        FindTimeRequest {
+            duration: TimeDelta::minutes(minutes),
+            first_day: NaiveDate::from_ymd_opt(2025, 1, 27).unwrap(),
+            days,
+            working_hours: (
+                NaiveTime::from_hms_opt(9, 0, 0).unwrap(),
+                NaiveTime::from_hms_opt(17, 0, 0).unwrap(),
+            ),
+            working_days: WORKING_DAYS.to_vec(),
+            calendars: Vec::new(),
+            source: BusySource::Local,
+        }
+    }
+
+    fn at(day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2025, 1, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    /// The Sunday before the request's first day
+    fn before() -> DateTime<Local> {
+        at(26, 0, 0)
+    }
+
+    /// The start, end and free until of each slot
+    fn times(slots: &[FreeSlot]) -> Vec<(DateTime<Local>, DateTime<Local>, DateTime<Local>)> {
+        slots
+            .iter()
+            .map(|slot| (slot.start, slot.end, slot.free_until))
+            .collect()
+    }
+
+    #[test]
+    fn free_days_have_a_slot_at_the_start_of_working_hours() {
+        let slots = find_free_slots(&request(30, 2), &[], before());
+        assert_eq!(
+            times(&slots),
+            [
+                (at(27, 9, 0), at(27, 9, 30), at(27, 17, 0)),
+                (at(28, 9, 0), at(28, 9, 30), at(28, 17, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn slots_are_found_between_busy_periods() {
+        let busy = [
+            (at(27, 11, 0), at(27, 12, 0)),
+            (at(27, 9, 0), at(27, 10, 0)),
+        ];
+        let slots = find_free_slots(&request(60, 1), &busy, before());
+        assert_eq!(
+            times(&slots),
+            [
+                (at(27, 10, 0), at(27, 11, 0), at(27, 11, 0)),
+                (at(27, 12, 0), at(27, 13, 0), at(27, 17, 0)),
+            ]
+        );
+    }
+
+    #[test]
+    fn gaps_too_short_for_the_meeting_are_skipped() {
+        let busy = [
+            (at(27, 9, 0), at(27, 10, 0)),
+            (at(27, 10, 20), at(27, 17, 0)),
+        ];
+        assert!(find_free_slots(&request(30, 1), &busy, before()).is_empty());
+    }
+
+    #[test]
+    fn overlapping_busy_periods_are_merged() {
+        let busy = [
+            (at(27, 9, 0), at(27, 11, 0)),
+            (at(27, 10, 0), at(27, 10, 30)),
+        ];
+        let slots = find_free_slots(&request(30, 1), &busy, before());
+        assert_eq!(
+            times(&slots),
+            [(at(27, 11, 0), at(27, 11, 30), at(27, 17, 0))]
+        );
+    }
+
+    #[test]
+    fn slots_start_on_a_quarter_hour() {
+        let busy = [(at(27, 9, 0), at(27, 9, 50))];
+        let slots = find_free_slots(&request(30, 1), &busy, before());
+        assert_eq!(slots[0].start, at(27, 10, 0));
+
+        // the search starts from now, rounded up the same way
+        let slots = find_free_slots(&request(30, 1), &[], at(27, 13, 7));
+        assert_eq!(
+            times(&slots),
+            [(at(27, 13, 15), at(27, 13, 45), at(27, 17, 0))]
+        );
+    }
+
+    #[test]
+    fn days_that_have_passed_or_arent_working_days_are_skipped() {
+        let mut request = request(30, 3);
+        // Saturday to Monday
+        request.first_day = NaiveDate::from_ymd_opt(2025, 1, 25).unwrap();
+        let slots = find_free_slots(&request, &[], at(24, 12, 0));
+        assert_eq!(
+            times(&slots),
+            [(at(27, 9, 0), at(27, 9, 30), at(27, 17, 0))]
+        );
+
+        let slots = find_free_slots(&request, &[], at(27, 16, 45));
+        assert!(slots.is_empty());
+    }
+
+    #[test]
+    fn busy_periods_can_cover_several_days() {
+        let busy = [(at(27, 0, 0), at(29, 0, 0))];
+        let slots = find_free_slots(&request(30, 3), &busy, before());
+        assert_eq!(
+            times(&slots),
+            [(at(29, 9, 0), at(29, 9, 30), at(29, 17, 0))]
+        );
+    }
+}
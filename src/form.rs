@@ -0,0 +1,104 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::theme::Theme;
+
+/// A popup of text fields, one per line with a label in front, moved between with Tab
+pub struct Form<const N: usize> {
+    title: &'static str,
+    /// what Enter does, shown at the bottom of the popup
+    submit: &'static str,
+    labels: [&'static str; N],
+    pub values: [String; N],
+    focused: usize,
+}
+
+/// What happened to a [`Form`] after it handled a key event
+pub enum FormEvent {
+    Editing,
+    Submitted,
+    Cancelled,
+}
+
+impl<const N: usize> Form<N> {
+    pub fn new(
+        title: &'static str,
+        submit: &'static str,
+        labels: [&'static str; N],
+        values: [String; N],
+    ) -> Self {
+        Form {
+            title,
+            submit,
+            labels,
+            values,
+            focused: 0,
+        }
+    }
+
+    pub fn on_key_event(&mut self, key: KeyEvent) -> FormEvent {
+        match key.code {
+            KeyCode::Enter => return FormEvent::Submitted,
+            KeyCode::Esc => return FormEvent::Cancelled,
+            KeyCode::Tab | KeyCode::Down => self.focused = (self.focused + 1) % N,
+            KeyCode::BackTab | KeyCode::Up => self.focused = (self.focused + N - 1) % N,
+            KeyCode::Backspace => _ = self.values[self.focused].pop(),
+            KeyCode::Char(c) => self.values[self.focused].push(c),
+            _ => {}
+        }
+        FormEvent::Editing
+    }
+}
+
+/// Draws `form` as a popup in the middle of the screen, with the labels lined up on the right
+pub fn draw_form<const N: usize>(frame: &mut Frame, form: &Form<N>, theme: &Theme) {
+    let vertical = Layout::vertical([Constraint::Length(N as u16 + 2)]).flex(Flex::Center);
+    let horizontal = Layout::horizontal([Constraint::Percentage(60)]).flex(Flex::Center);
+    let [area] = vertical.areas(frame.area());
+    let [area] = horizontal.areas(area);
+    frame.render_widget(Clear, area); // this clears out the background
+
+    let label_width = form
+        .labels
+        .iter()
+        .map(|label| label.len())
+        .max()
+        .unwrap_or_default();
+    let lines: Vec<Line> = form
+        .labels
+        .iter()
+        .zip(form.values.iter())
+        .enumerate()
+        .map(|(i, (label, value))| {
+            let label_style = if i == form.focused {
+                theme.selection
+            } else {
+                Style::default().add_modifier(Modifier::BOLD)
+            };
+            Line::from(vec![
+                Span::styled(format!("{label:>label_width$}"), label_style),
+                Span::from(format!(" {value}")),
+            ])
+        })
+        .collect();
+
+    let block = theme.popup_block().title(form.title).title_bottom(format!(
+        "[Tab] next field  [Enter] {}  [Esc] cancel",
+        form.submit
+    ));
+    frame.render_widget(Paragraph::new(lines).block(block), area);
+
+    // put the cursor at the end of the focused field
+    let cursor_x =
+        area.x + 1 + label_width as u16 + 1 + form.values[form.focused].chars().count() as u16;
+    frame.set_cursor_position((
+        cursor_x.min(area.right().saturating_sub(2)),
+        area.y + 1 + form.focused as u16,
+    ));
+}
@@ -1,14 +1,52 @@
 extern crate hyper;
 extern crate hyper_rustls;
 
-use std::{collections::HashMap, path::PathBuf, pin::Pin, future::Future};
 use chrono::naive::NaiveDate;
-use chrono::Local;
+use chrono::{DateTime, Local, Locale, TimeDelta, Utc};
+use std::{
+    collections::HashMap,
+    future::Future,
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+    pin::Pin,
+};
 
+use google_calendar3::{
+    api::{
+        CalendarListEntry, Colors, Event as CalendarEvent, EventDateTime, FreeBusyRequest,
+        FreeBusyRequestItem,
+    },
+    hyper_util,
+    yup_oauth2::{
+        self,
+        authenticator_delegate::{
+            DefaultDeviceFlowDelegate, DefaultInstalledFlowDelegate, DeviceAuthResponse,
+            DeviceFlowDelegate, InstalledFlowDelegate,
+        },
+    },
+    CalendarHub,
+};
+use localzone;
 use rustls;
-use google_calendar3::{api::{CalendarListEntry, Colors, Event as CalendarEvent, EventListCall}, hyper_util, yup_oauth2::{self, authenticator_delegate::{InstalledFlowDelegate, DefaultInstalledFlowDelegate}}, CalendarHub, Error};
 use serde::{Deserialize, Serialize};
-use localzone;
+use tempfile::NamedTempFile;
+use tokio::sync::mpsc::{self, UnboundedSender};
+use tokio::task::{JoinHandle, JoinSet};
+
+use crate::error::TalendarError;
+use crate::recurrence::{rules_continuing_after, rules_ending_at, until_before, RecurrenceScope};
+use crate::retry::RetryDelegate;
+use crate::utils::{date_string, time_string};
+
+/// Every scope the calendar api calls ask for. Logging in with all of them at once means a single
+/// token covers every call, so the user is only asked once.
+pub const SCOPES: [&str; 2] = [
+    "https://www.googleapis.com/auth/calendar",
+    "https://www.googleapis.com/auth/calendar.readonly",
+];
+
+type Hub =
+    CalendarHub<hyper_rustls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector>>;
 
 #[derive(Serialize, Deserialize, Default)]
 pub struct CalendarCache {
@@ -21,339 +59,1504 @@ pub struct CalendarCache {
     pub calendars: Vec<CalendarListEntry>,
 
     pub colors: Colors,
+
+    // map from event ID to the ID of the calendar it belongs to
+    #[serde(default)]
+    event_calendar_ids: HashMap<String, String>,
+
+    // map from the key of each dismissed reminder to the start of its event
+    #[serde(default)]
+    dismissed_reminders: HashMap<String, DateTime<Utc>>,
+
+    // map from calendar ID to the sync token of its recurring series
+    #[serde(default)]
+    series_sync_tokens: HashMap<String, String>,
+
+    // map from event ID to the master event of each recurring series, which has the recurrence
+    // rules that its expanded instances in `events` don't
+    #[serde(default)]
+    recurring_events: HashMap<String, CalendarEvent>,
+
+    // map from event ID to each instance of a recurring series that was changed or cancelled
+    #[serde(default)]
+    recurrence_exceptions: HashMap<String, CalendarEvent>,
+
+    // when the last sync finished
+    #[serde(default)]
+    last_synced: Option<DateTime<Utc>>,
+}
+
+/// Everything a sync fetched from the api, to be merged into the cache with
+/// [`CalendarClient::apply_sync`]
+pub struct SyncedData {
+    calendars: Vec<CalendarListEntry>,
+    colors: Colors,
+    changes: Vec<CalendarChanges>,
+    failed: Vec<FailedCalendar>,
+}
+
+/// How far through its calendars a sync is
+#[derive(Debug, Clone, Copy)]
+pub struct SyncProgress {
+    /// the calendars that have finished syncing, or failed to
+    pub synced: usize,
+    pub total: usize,
+}
+
+/// When some calendars are busy, according to Google
+pub struct FreeBusy {
+    pub busy: Vec<(DateTime<Utc>, DateTime<Utc>)>,
+    /// the names of the calendars Google couldn't check, like those of people who don't share
+    /// their free time with us, which may be busy at any time
+    pub unchecked: Vec<String>,
+}
+
+/// A calendar that couldn't be synced, which keeps its cached events until it can be
+pub struct FailedCalendar {
+    pub name: String,
+    pub error: TalendarError,
+}
+
+/// The events of one calendar that changed since it was last synced
+struct CalendarChanges {
+    calendar_id: String,
+    /// changed events, with recurring events expanded into their instances
+    events: Vec<CalendarEvent>,
+    sync_token: Option<String>,
+    /// changed master events of recurring series, and changed or cancelled instances
+    series_events: Vec<CalendarEvent>,
+    series_sync_token: Option<String>,
 }
 
 impl CalendarCache {
-    /// add event to `events` hash map, creating a new vec entry if one does not already exist
-    fn add_event(&mut self, event: CalendarEvent) {
-        let date  = event.get_naive_date().unwrap();
+    /// Loads the cache at `cache_path`. If it doesn't exist or can't be read, we start with an
+    /// empty one.
+    pub fn load(cache_path: &Path) -> Self {
+        let mut cache: CalendarCache = std::fs::read(cache_path)
+            .ok()
+            .and_then(|contents| serde_json::from_slice(&contents).ok())
+            .unwrap_or_default();
+        // caches written before events were kept in order need sorting once
+        for events in cache.events.values_mut() {
+            events.sort_by_key(event_order);
+        }
+        cache
+    }
+
+    /// Returns the events that are on at any time between `start` and `end`, in the order they
+    /// start. Events that started before `start` but haven't ended are included.
+    pub fn events_between(
+        &self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Vec<&CalendarEvent> {
+        let mut events: Vec<(DateTime<Local>, &CalendarEvent)> = self
+            .events
+            .values()
+            .flatten()
+            .filter_map(|event| {
+                let (event_start, event_end) = event.get_local_bounds()?;
+                (event_start < end && event_end > start).then_some((event_start, event))
+            })
+            .collect();
+        events.sort_by_key(|(event_start, _)| *event_start);
+        events.into_iter().map(|(_, event)| event).collect()
+    }
+
+    /// add event to `events` hash map, creating a new vec entry if one does not already exist, and
+    /// remember which calendar it came from
+    fn add_event(&mut self, event: CalendarEvent, calendar_id: &str) {
+        if let Some(event_id) = &event.id {
+            // if we already have this event then this is a newer version of it, which might be on
+            // a different day, so get rid of the old one
+            if self.event_calendar_ids.contains_key(event_id) {
+                self.remove_event_by_id(event_id);
+            }
+            self.event_calendar_ids
+                .insert(event_id.clone(), calendar_id.to_string());
+        }
+
+        let Some(date) = event.get_naive_date() else {
+            return;
+        };
         match self.events.get_mut(&date) {
             // If there is no vec in the hash map, then we need to create one
             None => {
                 let vec = vec![event];
                 _ = self.events.insert(date, vec);
             }
-            // if there is already a vec, then we need to add our event (todo in order?)
+            // if there is already a vec, then we need to add our event, keeping the day in order
             Some(vec) => {
-                vec.push(event);
+                let index = vec.partition_point(|existing_event| {
+                    event_order(existing_event) <= event_order(&event)
+                });
+                vec.insert(index, event);
             }
         }
     }
 
-    /// remove event from vec in `events` hash map
-    fn remove_event(&mut self, event: CalendarEvent) {
-        let date = get_event_naive_date(&event).unwrap_or_default();
-        match self.events.get_mut(&date) {
-            Some(vec) => {
-                vec.retain(|existing_event| {
-                    existing_event.id.as_ref().unwrap() != event.id.as_ref().unwrap()
-                });
+    /// Merges the changed events of a calendar into the cache, and keeps its sync tokens for
+    /// next time
+    fn apply_changes(&mut self, changes: CalendarChanges) {
+        let calendar_id = changes.calendar_id.as_str();
+        for event in changes.events {
+            if event.status.as_deref() == Some("cancelled") {
+                // cancelled events might only have an id, so they can't be looked up by date
+                if let Some(event_id) = &event.id {
+                    self.remove_event_by_id(event_id);
+                    self.event_calendar_ids.remove(event_id);
+                }
+            } else if event.get_naive_date().is_some() {
+                self.add_event(event, calendar_id);
             }
-            None => {
-                eprintln!("Tried to remove an event already missing from cache");
+        }
+        if let Some(token) = changes.sync_token {
+            self.sync_tokens.insert(calendar_id.to_string(), token);
+        }
+
+        for event in changes.series_events {
+            let Some(event_id) = event.id.clone() else {
+                continue;
+            };
+            let is_cancelled = event.status.as_deref() == Some("cancelled");
+
+            if event.recurrence.is_some()
+                || (is_cancelled && self.recurring_events.contains_key(&event_id))
+            {
+                if is_cancelled {
+                    self.recurring_events.remove(&event_id);
+                } else {
+                    self.event_calendar_ids
+                        .insert(event_id.clone(), calendar_id.to_string());
+                    self.recurring_events.insert(event_id, event);
+                }
+            } else if event.recurring_event_id.is_some() {
+                // cancelled instances are kept too, they're occurrences that were deleted
+                self.recurrence_exceptions.insert(event_id, event);
             }
         }
+
+        // exceptions of series that are gone are no use to anyone
+        let recurring_events = &self.recurring_events;
+        self.recurrence_exceptions.retain(|_, exception| {
+            exception
+                .recurring_event_id
+                .as_ref()
+                .is_some_and(|id| recurring_events.contains_key(id))
+        });
+
+        if let Some(token) = changes.series_sync_token {
+            self.series_sync_tokens
+                .insert(calendar_id.to_string(), token);
+        }
+    }
+
+    /// Returns the calendar that `event` was synced from, if we know it
+    pub fn event_calendar(&self, event: &CalendarEvent) -> Option<&CalendarListEntry> {
+        let calendar_id = self.event_calendar_ids.get(event.id.as_ref()?)?;
+        self.calendars
+            .iter()
+            .find(|calendar| calendar.id.as_ref() == Some(calendar_id))
+    }
+
+    /// The account the cache belongs to, which is the id of its primary calendar
+    pub fn primary_account(&self) -> Option<String> {
+        self.calendars
+            .iter()
+            .find(|calendar| calendar.primary == Some(true))
+            .and_then(|calendar| calendar.id.clone())
+    }
+
+    /// remove every event with id `event_id`, whichever day it's on
+    fn remove_event_by_id(&mut self, event_id: &str) {
+        for events in self.events.values_mut() {
+            events.retain(|existing_event| existing_event.id.as_deref() != Some(event_id));
+        }
+    }
+
+    /// remove the expanded instances of the series with id `recurring_event_id` that start at or
+    /// after `from`, or all of them if `from` is `None`
+    fn remove_instances(&mut self, recurring_event_id: &str, from: Option<DateTime<Local>>) {
+        let is_removed = |event: &CalendarEvent| {
+            event.recurring_event_id.as_deref() == Some(recurring_event_id)
+                && from.is_none_or(|from| {
+                    event
+                        .get_local_bounds()
+                        .is_some_and(|(start, _)| start >= from)
+                })
+        };
+        for events in self.events.values_mut() {
+            events.retain(|event| !is_removed(event));
+        }
     }
 }
 
 pub struct CalendarClient {
-    hub: CalendarHub<hyper_rustls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector>>,
+    hub: Hub,
     pub cache: CalendarCache,
     cache_path: PathBuf,
 }
 
+/// How we log in to Google when we don't have a token yet
+pub enum LoginFlow {
+    /// show a url to log in at in the browser, which sends the login back to a server on
+    /// `redirect_port`, or on any free port if it's `None`
+    Browser {
+        delegate: Box<dyn InstalledFlowDelegate>,
+        redirect_port: Option<u16>,
+    },
+    /// show a code to enter at a url on any other device, for when there's no browser here.
+    /// This is the OAuth device authorization grant.
+    DeviceCode {
+        delegate: Box<dyn DeviceFlowDelegate>,
+    },
+    /// never log in, so only a token we already have is used. Getting a token fails instead of
+    /// showing a login when there isn't one, or when it can't be refreshed.
+    ExistingToken,
+}
+
+impl LoginFlow {
+    /// Logs in in the browser, printing the url to stdout in case it doesn't open
+    pub fn browser(redirect_port: Option<u16>) -> Self {
+        LoginFlow::Browser {
+            delegate: Box::new(InstalledFlowBrowserDelegate),
+            redirect_port,
+        }
+    }
+
+    /// Logs in with a code, printing the code and url to stdout
+    pub fn device_code() -> Self {
+        LoginFlow::DeviceCode {
+            delegate: Box::new(DefaultDeviceFlowDelegate),
+        }
+    }
+}
+
+/// Only lets the owner of the file at `path` read and write it, if there is a file there
+pub fn restrict_to_owner(path: &Path) {
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        if path.exists() {
+            let _ = std::fs::set_permissions(path, std::fs::Permissions::from_mode(0o600));
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = path;
+}
+
 impl CalendarClient {
-    pub async fn new(cache_path: PathBuf) -> Result<Self, Error> {
+    /// Connects to the calendar api, logging in with `login_flow` if there's no token in
+    /// `token_path` yet
+    pub async fn new(
+        cache_path: PathBuf,
+        token_path: &Path,
+        login_flow: LoginFlow,
+    ) -> Result<Self, TalendarError> {
         // Create cache directory if it doesn't exist
         if let Some(parent) = cache_path.parent() {
-            let _ = std::fs::create_dir_all(parent);
-        } 
-
-        // If the cache exists, load it. If it doesn't exist, create an empty one
-        let cache: CalendarCache = if cache_path.exists() {
-            let file = std::fs::File::open(&cache_path)
-                .expect("Error loading the cache");
-            serde_json::from_reader(file).unwrap_or_default()
-        } else {
-            CalendarCache::default()
-        };
+            std::fs::create_dir_all(parent).map_err(|source| TalendarError::Cache {
+                path: parent.to_path_buf(),
+                source,
+            })?;
+        }
 
-        let hub = initialize_calendar_hub(&cache_path);
+        let cache = CalendarCache::load(&cache_path);
 
-        Ok( Self{ 
-            hub: hub.await,
+        let hub = initialize_calendar_hub(token_path, login_flow);
+
+        Ok(Self {
+            hub: hub.await?,
             cache,
             cache_path,
         })
     }
 
-    fn save_cache(&self) -> Result<(), Error> {
-        let file = std::fs::File::create(&self.cache_path)
-            .expect("Error creating the cache file");
+    /// Makes sure we have a token for every scope in [`SCOPES`], logging in if we don't. Tokens
+    /// are cached, so this only asks the user to log in the first time or after they're revoked.
+    pub async fn authenticate(&self) -> Result<(), Box<dyn std::error::Error + Send + Sync>> {
+        self.hub.auth.get_token(&SCOPES).await.map(|_| ())
+    }
+
+    /// Returns an access token, refreshing it if it has expired
+    pub async fn access_token(
+        &self,
+    ) -> Result<Option<String>, Box<dyn std::error::Error + Send + Sync>> {
+        self.hub.auth.get_token(&SCOPES).await
+    }
+
+    /// Fetches the account we're logged in as, which is the id of the primary calendar
+    pub async fn account(&self) -> Result<Option<String>, TalendarError> {
+        let (_, calendar) = self
+            .hub
+            .calendars()
+            .get("primary")
+            .delegate(&mut RetryDelegate::new())
+            .doit()
+            .await?;
+        Ok(calendar.id)
+    }
 
-        _ = serde_json::to_writer_pretty(file, &self.cache);
+    /// Writes the cache to a temporary file and moves it over the old cache, so that anything
+    /// reading the cache at the same time (like `talendar status`) never sees half of it. Each
+    /// save has a temporary file of its own, so two talendars saving at once can't mix them up.
+    pub fn save_cache(&self) -> Result<(), TalendarError> {
+        let cache_error = |source| TalendarError::Cache {
+            path: self.cache_path.clone(),
+            source,
+        };
+        let directory = self.cache_path.parent().unwrap_or(Path::new("."));
+        let mut file = NamedTempFile::new_in(directory).map_err(cache_error)?;
 
+        let mut writer = BufWriter::new(file.as_file_mut());
+        serde_json::to_writer(&mut writer, &self.cache).map_err(|err| cache_error(err.into()))?;
+        writer.flush().map_err(cache_error)?;
+        drop(writer);
+        file.persist(&self.cache_path)
+            .map_err(|err| cache_error(err.error))?;
         Ok(())
     }
 
-    /// Fetches the calendar list from the google calendar api, and calls incremental_sync_events
-    /// on each calendar therin to sync all of the events in each calendar.
-    /// Fetches the Colors.
-    pub async fn sync(&mut self) -> Result<(), google_calendar3::Error> {
-        let calendars = self.hub.calendar_list()
-            .list()
+    /// Fetches the calendar list, the changed events in each calendar and the colors from the
+    /// google calendar api, and merges them into the cache. Returns the calendars that couldn't
+    /// be synced. Up to `concurrency` calendars are synced at once.
+    pub async fn sync(&mut self, concurrency: usize) -> Result<Vec<FailedCalendar>, TalendarError> {
+        // nobody's watching the progress
+        let (progress, _) = mpsc::unbounded_channel();
+        let data = fetch_sync(
+            self.hub.clone(),
+            self.cache.sync_tokens.clone(),
+            self.cache.series_sync_tokens.clone(),
+            concurrency,
+            progress,
+        )
+        .await?;
+        self.apply_sync(data)
+    }
+
+    /// Starts fetching what [`CalendarClient::sync`] would in the background, so that the cache
+    /// can still be used in the meantime. `progress` is told each time a calendar has been
+    /// fetched. The result is merged into the cache with [`CalendarClient::apply_sync`].
+    pub fn start_sync(
+        &self,
+        concurrency: usize,
+        progress: UnboundedSender<SyncProgress>,
+    ) -> JoinHandle<Result<SyncedData, TalendarError>> {
+        tokio::spawn(fetch_sync(
+            self.hub.clone(),
+            self.cache.sync_tokens.clone(),
+            self.cache.series_sync_tokens.clone(),
+            concurrency,
+            progress,
+        ))
+    }
+
+    /// Merges what a sync fetched into the cache, and saves it. Returns the calendars that
+    /// couldn't be synced.
+    pub fn apply_sync(&mut self, data: SyncedData) -> Result<Vec<FailedCalendar>, TalendarError> {
+        self.cache.calendars = data.calendars;
+        for changes in data.changes {
+            self.cache.apply_changes(changes);
+        }
+        self.cache.colors = data.colors;
+        self.cache.last_synced = Some(Utc::now());
+        self.save_cache()?;
+        Ok(data.failed)
+    }
+
+    /// When the last sync finished, if there has been one
+    pub fn last_synced(&self) -> Option<DateTime<Local>> {
+        self.cache
+            .last_synced
+            .map(|last_synced| last_synced.with_timezone(&Local))
+    }
+
+    pub fn get_calendars(&self) -> &[CalendarListEntry] {
+        &self.cache.calendars
+    }
+
+    pub fn get_events_by_date(&self, date: &NaiveDate) -> Option<&Vec<CalendarEvent>> {
+        self.cache.events.get(date)
+    }
+
+    /// Returns the cached event with id `event_id`, whichever day it's on
+    pub fn get_event(&self, event_id: &str) -> Option<&CalendarEvent> {
+        self.cache
+            .events
+            .values()
+            .flatten()
+            .find(|event| event.id.as_deref() == Some(event_id))
+    }
+
+    /// Returns the events that are on at any time between `start` and `end`, in the order they
+    /// start
+    pub fn get_events_between(
+        &self,
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    ) -> Vec<&CalendarEvent> {
+        self.cache.events_between(start, end)
+    }
+
+    /// Sets our response to the invitation `event`, optionally with a comment for the organizer.
+    /// The whole attendee list has to be sent with the patch, but only our own entry is changed.
+    pub async fn respond_to_event(
+        &mut self,
+        event: &CalendarEvent,
+        response: ResponseStatus,
+        comment: Option<String>,
+    ) -> Result<(), TalendarError> {
+        let (Some(event_id), Some(mut attendees)) = (event.id.clone(), event.attendees.clone())
+        else {
+            // nobody was invited to this event, so there's nothing to respond to
+            return Ok(());
+        };
+
+        for attendee in attendees
+            .iter_mut()
+            .filter(|attendee| attendee.self_ == Some(true))
+        {
+            attendee.response_status = Some(response.as_str().to_string());
+            if comment.is_some() {
+                attendee.comment = comment.clone();
+            }
+        }
+
+        let calendar_id = self.event_calendar_id(event);
+        let patch = CalendarEvent {
+            attendees: Some(attendees),
+            ..Default::default()
+        };
+        let (_, updated_event) = self
+            .hub
+            .events()
+            .patch(patch, &calendar_id, &event_id)
+            .delegate(&mut RetryDelegate::new())
             .doit()
-            .await?
-            .1  // doit() returns Result<(Response, CalendarList)>
-            .items
-            .unwrap_or_default();
+            .await?;
+
+        // the updated event replaces the old one, wherever it was
+        self.cache.add_event(updated_event, &calendar_id);
+        self.save_cache()?;
+
+        Ok(())
+    }
 
-        // Sync the calendar list
-        self.cache.calendars = calendars.clone();
+    /// Asks the FreeBusy API when any of `calendar_ids` are busy between `time_min` and `time_max`.
+    /// The ids can be calendar ids or the email addresses of other people.
+    pub async fn query_free_busy(
+        &self,
+        calendar_ids: &[String],
+        time_min: DateTime<Utc>,
+        time_max: DateTime<Utc>,
+    ) -> Result<FreeBusy, TalendarError> {
+        let request = FreeBusyRequest {
+            items: Some(
+                calendar_ids
+                    .iter()
+                    .map(|id| FreeBusyRequestItem {
+                        id: Some(id.clone()),
+                    })
+                    .collect(),
+            ),
+            time_min: Some(time_min),
+            time_max: Some(time_max),
+            ..Default::default()
+        };
+
+        let (_, response) = self
+            .hub
+            .freebusy()
+            .query(request)
+            .delegate(&mut RetryDelegate::new())
+            .doit()
+            .await?;
 
-        // Sync the events in each calendar
-        for calendar in calendars {
-            self.incremental_sync_events(&calendar).await?;
+        let mut free_busy = FreeBusy {
+            busy: Vec::new(),
+            unchecked: Vec::new(),
+        };
+        for (calendar_id, calendar) in response.calendars.unwrap_or_default() {
+            if calendar.errors.is_some_and(|errors| !errors.is_empty()) {
+                // our own calendars are shown by name, anyone else's by their email
+                let name = self
+                    .cache
+                    .calendars
+                    .iter()
+                    .find(|calendar| calendar.id.as_ref() == Some(&calendar_id))
+                    .and_then(|calendar| {
+                        calendar
+                            .summary_override
+                            .clone()
+                            .or(calendar.summary.clone())
+                    });
+                free_busy.unchecked.push(name.unwrap_or(calendar_id));
+            }
+            free_busy.busy.extend(
+                calendar
+                    .busy
+                    .unwrap_or_default()
+                    .into_iter()
+                    .filter_map(|period| Some((period.start?, period.end?))),
+            );
         }
+        free_busy.unchecked.sort();
 
-        // Fetch the colors
-        let _ = self.fetch_colors().await?;
+        Ok(free_busy)
+    }
 
-        self.save_cache()
+    /// Works out when any of `calendar_ids` are busy between `first_day` and `last_day` from the
+    /// events in the cache. This only knows about calendars that we sync. An empty `calendar_ids`
+    /// means all of them.
+    pub fn local_busy_periods(
+        &self,
+        calendar_ids: &[String],
+        first_day: NaiveDate,
+        last_day: NaiveDate,
+    ) -> Vec<(DateTime<Local>, DateTime<Local>)> {
+        let in_calendars = |event: &CalendarEvent| {
+            calendar_ids.is_empty()
+                || event
+                    .id
+                    .as_ref()
+                    .and_then(|event_id| self.cache.event_calendar_ids.get(event_id))
+                    .is_some_and(|calendar_id| calendar_ids.contains(calendar_id))
+        };
 
+        // events are stored under the day they start, so look back a day for overnight events
+        first_day
+            .pred_opt()
+            .unwrap_or(first_day)
+            .iter_days()
+            .take_while(|date| *date <= last_day)
+            .filter_map(|date| self.get_events_by_date(&date))
+            .flatten()
+            .filter(|event| in_calendars(event))
+            .filter(|event| {
+                event.transparency.as_deref() != Some("transparent") && !event.is_declined()
+            })
+            .filter_map(|event| event.get_local_bounds())
+            .collect()
     }
 
-    /// incremental_sync_events
-    /// -- calls EventListCall::sync_token to get the latest sync token
-    /// -- updates self.cache.events accordingly
-    /// -- stores the new sync token for next time
-    async fn incremental_sync_events(&mut self, calendar: &CalendarListEntry) -> Result<(), google_calendar3::Error> {
-        let calendar_id = calendar.id.clone().unwrap();
-        let calendar_id_str = calendar_id.as_str();
-        let local_timezone = localzone::get_local_zone().unwrap_or("Australia/Melbourne".to_string());
+    /// Returns the id of the calendar with the name `name_or_id`, or `name_or_id` itself if none
+    /// of our calendars are called that
+    pub fn resolve_calendar_id(&self, name_or_id: &str) -> String {
+        self.cache
+            .calendars
+            .iter()
+            .find(|calendar| {
+                let name = calendar
+                    .summary_override
+                    .as_ref()
+                    .or(calendar.summary.as_ref());
+                name.is_some_and(|name| name.eq_ignore_ascii_case(name_or_id))
+            })
+            .and_then(|calendar| calendar.id.clone())
+            .unwrap_or(name_or_id.to_string())
+    }
 
-        let get_request = || { self.hub
+    /// Creates `event` in the calendar `calendar_id`, and adds the created event to the cache
+    pub async fn insert_event(
+        &mut self,
+        calendar_id: &str,
+        event: CalendarEvent,
+    ) -> Result<CalendarEvent, TalendarError> {
+        let (_, created_event) = self
+            .hub
             .events()
-            .list(calendar_id_str)
-            .single_events(true)
-            .time_zone(&local_timezone)
-            .max_results(2500)};
+            .insert(event, calendar_id)
+            .send_updates("all")
+            .delegate(&mut RetryDelegate::for_create())
+            .doit()
+            .await?;
 
+        if created_event.get_naive_date().is_some() {
+            self.cache.add_event(created_event.clone(), calendar_id);
+            self.save_cache()?;
+        }
 
-        let mut request: EventListCall<'_, hyper_rustls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector>>;
+        Ok(created_event)
+    }
 
-        // use sync token if we have one
-        if let Some(token) = self.cache.sync_tokens.get(calendar_id_str) {
-            request = get_request().sync_token(token);
-        } else {
-            request = get_request();
+    /// Creates an event in the calendar `calendar_id` from a line of text like "Lunch with Sam
+    /// tomorrow 1pm", using Google's parser. The created event is added to the cache.
+    pub async fn quick_add_event(
+        &mut self,
+        calendar_id: &str,
+        text: &str,
+    ) -> Result<CalendarEvent, TalendarError> {
+        let (_, created_event) = self
+            .hub
+            .events()
+            .quick_add(calendar_id, text)
+            .delegate(&mut RetryDelegate::for_create())
+            .doit()
+            .await?;
+
+        if created_event.get_naive_date().is_some() {
+            self.cache.add_event(created_event.clone(), calendar_id);
+            self.save_cache()?;
         }
 
-        let mut response = request.doit().await?.1;
+        Ok(created_event)
+    }
+
+    /// Returns `true` if the reminder with key `reminder_key` has been dismissed
+    pub fn is_reminder_dismissed(&self, reminder_key: &str) -> bool {
+        self.cache.dismissed_reminders.contains_key(reminder_key)
+    }
+
+    /// Remembers that the reminder with key `reminder_key` was dismissed, so that it doesn't fire
+    /// again. Dismissed reminders of events that started more than a day ago are forgotten.
+    pub fn dismiss_reminder(
+        &mut self,
+        reminder_key: &str,
+        event_start: DateTime<Local>,
+    ) -> Result<(), TalendarError> {
+        let cutoff = Utc::now() - TimeDelta::days(1);
+        self.cache
+            .dismissed_reminders
+            .retain(|_, start| *start > cutoff);
+        self.cache
+            .dismissed_reminders
+            .insert(reminder_key.to_string(), event_start.to_utc());
+        self.save_cache()
+    }
+
+    /// Puts `event` in the cache in place of any event with the same id, to show a change that
+    /// hasn't been sent to Google yet. The cache isn't saved.
+    pub fn show_unsent_event(&mut self, calendar_id: &str, event: CalendarEvent) {
+        self.cache.add_event(event, calendar_id);
+    }
 
-        // loop through all the pages
-        loop {
-            // Handle the events we received
-            if let Some(events) = response.items {
-                for event in events {
+    /// Takes the event with id `event_id` out of the cache, to show a deletion or take back an
+    /// event that hasn't been sent to Google yet. The cache isn't saved.
+    pub fn hide_unsent_event(&mut self, event_id: &str) {
+        self.cache.remove_event_by_id(event_id);
+        self.cache.event_calendar_ids.remove(event_id);
+    }
+
+    /// Returns the calendar that `event` was synced from, if we know it
+    pub fn get_event_calendar(&self, event: &CalendarEvent) -> Option<&CalendarListEntry> {
+        self.cache.event_calendar(event)
+    }
+
+    /// Returns the id of the calendar that `event` was synced from. Invitations end up in our
+    /// primary calendar, so that's the best guess if we don't know.
+    pub fn event_calendar_id(&self, event: &CalendarEvent) -> String {
+        event
+            .id
+            .as_ref()
+            .and_then(|event_id| self.cache.event_calendar_ids.get(event_id))
+            .cloned()
+            .unwrap_or(String::from("primary"))
+    }
+
+    /// Returns the master event of the recurring series that `event` is an instance of, or
+    /// `event` itself if it is a master event
+    pub fn get_recurring_event<'a>(
+        &'a self,
+        event: &'a CalendarEvent,
+    ) -> Option<&'a CalendarEvent> {
+        if event.recurrence.is_some() {
+            return Some(event);
+        }
+        self.cache
+            .recurring_events
+            .get(event.recurring_event_id.as_ref()?)
+    }
+
+    /// Syncs the events in the calendar `calendar_id`, after a change that affects more events
+    /// than the one we sent
+    async fn sync_calendar(&mut self, calendar_id: &str) -> Result<(), TalendarError> {
+        let changes = fetch_calendar_changes(
+            &self.hub,
+            calendar_id,
+            self.cache.sync_tokens.get(calendar_id).cloned(),
+            self.cache.series_sync_tokens.get(calendar_id).cloned(),
+        )
+        .await?;
+        self.cache.apply_changes(changes);
+        self.save_cache()
+    }
+
+    /// Puts the event back exactly the way it is in `snapshot`. A deleted event is brought back,
+    /// since Google keeps deleted events as cancelled.
+    pub async fn restore_event(
+        &mut self,
+        calendar_id: &str,
+        snapshot: &CalendarEvent,
+    ) -> Result<(), TalendarError> {
+        let Some(event_id) = snapshot.id.as_deref() else {
+            return Ok(());
+        };
+        // an update replaces the whole event, so every field is put back, including any that were
+        // empty. The ones Google keeps track of itself are left for it to fill in.
+        let event = CalendarEvent {
+            status: Some(snapshot.status.clone().unwrap_or(String::from("confirmed"))),
+            etag: None,
+            sequence: None,
+            created: None,
+            updated: None,
+            ..snapshot.clone()
+        };
+        let (_, restored_event) = self
+            .hub
+            .events()
+            .update(event, calendar_id, event_id)
+            .send_updates("all")
+            .delegate(&mut RetryDelegate::new())
+            .doit()
+            .await?;
+        self.cache.add_event(restored_event, calendar_id);
+        self.save_cache()
+    }
 
-                    if let Some(_date) = get_event_naive_date(&event) {
+    /// Deletes `event`, or the events of its series in `scope`. Deleting this and the following
+    /// events ends the series just before `event`.
+    pub async fn delete_event(
+        &mut self,
+        event: &CalendarEvent,
+        scope: RecurrenceScope,
+    ) -> Result<(), TalendarError> {
+        let calendar_id = self.event_calendar_id(event);
+        let split = self.series_split(event, scope);
 
-                        if let Some(status) = &event.status {
-                            match status.as_str() {
-                                "cancelled" => self.cache.remove_event(event) ,
-                                _ => self.cache.add_event(event),
-                            }
-                        } else {
-                            self.cache.add_event(event);
-                        }
-                    }
+        match split {
+            SeriesSplit::Instance => {
+                let Some(event_id) = event.id.as_deref() else {
+                    return Ok(());
+                };
+                self.hub
+                    .events()
+                    .delete(&calendar_id, event_id)
+                    .send_updates("all")
+                    .delegate(&mut RetryDelegate::new())
+                    .doit()
+                    .await?;
+                self.cache.remove_event_by_id(event_id);
+                self.cache.event_calendar_ids.remove(event_id);
+                self.save_cache()?;
+            }
+            SeriesSplit::Whole(master) => {
+                let Some(master_id) = master.id.as_deref() else {
+                    return Ok(());
+                };
+                self.hub
+                    .events()
+                    .delete(&calendar_id, master_id)
+                    .send_updates("all")
+                    .delegate(&mut RetryDelegate::new())
+                    .doit()
+                    .await?;
+                self.cache.remove_instances(master_id, None);
+                self.cache.recurring_events.remove(master_id);
+                self.save_cache()?;
+            }
+            SeriesSplit::At {
+                master,
+                start,
+                until,
+            } => {
+                let Some(master_id) = master.id.as_deref() else {
+                    return Ok(());
+                };
+                let rules = master.recurrence.clone().unwrap_or_default();
+                let patch = CalendarEvent {
+                    recurrence: Some(rules_ending_at(&rules, &until)),
+                    ..Default::default()
+                };
+                self.hub
+                    .events()
+                    .patch(patch, &calendar_id, master_id)
+                    .send_updates("all")
+                    .delegate(&mut RetryDelegate::new())
+                    .doit()
+                    .await?;
+                if let Some(from) = event_date_time_to_local(&start) {
+                    self.cache.remove_instances(master_id, Some(from));
                 }
-                // handle pagination
-                match response.next_page_token {
-                    Some(token) => {
-                        // use sync token if we have one
-                        if let Some(token) = self.cache.sync_tokens.get(calendar_id_str) {
-                            request = get_request().sync_token(token);
-                        } else {
-                            request = get_request();
-                        }
-                        request = request.page_token(&token);
-                        response = request.doit().await?.1;
-                    }
-                    None => break
+                self.sync_calendar(&calendar_id).await?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Changes the title, location, start and end of `event`, or of the events of its series in
+    /// `scope`, to those in `changes`. Changing the time of a series moves every event in it by
+    /// as much as `event` moved. Changing this and the following events ends the series just
+    /// before `event`, and starts a new series with the changes.
+    pub async fn update_event(
+        &mut self,
+        event: &CalendarEvent,
+        changes: CalendarEvent,
+        scope: RecurrenceScope,
+    ) -> Result<(), TalendarError> {
+        let calendar_id = self.event_calendar_id(event);
+        let split = self.series_split(event, scope);
+
+        match split {
+            SeriesSplit::Instance => {
+                let Some(event_id) = event.id.as_deref() else {
+                    return Ok(());
+                };
+                let (_, updated_event) = self
+                    .hub
+                    .events()
+                    .patch(changes, &calendar_id, event_id)
+                    .send_updates("all")
+                    .delegate(&mut RetryDelegate::new())
+                    .doit()
+                    .await?;
+                self.cache.add_event(updated_event, &calendar_id);
+                self.save_cache()?;
+            }
+            SeriesSplit::Whole(master) => {
+                let Some(master_id) = master.id.as_deref() else {
+                    return Ok(());
+                };
+                let patch = CalendarEvent {
+                    summary: changes.summary.clone(),
+                    location: changes.location.clone(),
+                    start: shifted_event_date_time(
+                        master.start.as_ref(),
+                        event.start.as_ref(),
+                        changes.start.as_ref(),
+                    ),
+                    end: shifted_event_date_time(
+                        master.end.as_ref(),
+                        event.end.as_ref(),
+                        changes.end.as_ref(),
+                    ),
+                    ..Default::default()
+                };
+                self.hub
+                    .events()
+                    .patch(patch, &calendar_id, master_id)
+                    .send_updates("all")
+                    .delegate(&mut RetryDelegate::new())
+                    .doit()
+                    .await?;
+                self.sync_calendar(&calendar_id).await?;
+            }
+            SeriesSplit::At {
+                master,
+                start,
+                until,
+            } => {
+                let Some(master_id) = master.id.as_deref() else {
+                    return Ok(());
+                };
+                let rules = master.recurrence.clone().unwrap_or_default();
+                let occurrences_before = self.occurrences_before(master_id, &start);
+
+                let patch = CalendarEvent {
+                    recurrence: Some(rules_ending_at(&rules, &until)),
+                    ..Default::default()
                 };
+                self.hub
+                    .events()
+                    .patch(patch, &calendar_id, master_id)
+                    .send_updates("all")
+                    .delegate(&mut RetryDelegate::new())
+                    .doit()
+                    .await?;
+                if let Some(from) = event_date_time_to_local(&start) {
+                    self.cache.remove_instances(master_id, Some(from));
+                }
 
-            } else {
-                // if we didn't receive any events then we're done
-                break
+                // recurring events need a time zone to know when the events happen
+                let time_zone = master
+                    .start
+                    .as_ref()
+                    .and_then(|start| start.time_zone.clone())
+                    .or(localzone::get_local_zone());
+                let with_time_zone = |date_time: Option<EventDateTime>| {
+                    date_time.map(|date_time| EventDateTime {
+                        time_zone: date_time.date_time.and(time_zone.clone()),
+                        ..date_time
+                    })
+                };
+                let new_series = CalendarEvent {
+                    summary: changes.summary.or(master.summary.clone()),
+                    location: changes.location.or(master.location.clone()),
+                    description: master.description.clone(),
+                    attendees: master.attendees.clone(),
+                    reminders: master.reminders.clone(),
+                    color_id: master.color_id.clone(),
+                    transparency: master.transparency.clone(),
+                    visibility: master.visibility.clone(),
+                    start: with_time_zone(changes.start),
+                    end: with_time_zone(changes.end),
+                    recurrence: Some(rules_continuing_after(&rules, occurrences_before)),
+                    ..Default::default()
+                };
+                self.hub
+                    .events()
+                    .insert(new_series, &calendar_id)
+                    .send_updates("all")
+                    .delegate(&mut RetryDelegate::for_create())
+                    .doit()
+                    .await?;
+                self.sync_calendar(&calendar_id).await?;
             }
         }
 
+        Ok(())
+    }
 
-        // Store the new sync token
-        if let Some(token) = response.next_sync_token {
-            self.cache.sync_tokens.insert(calendar_id, token);
+    /// Works out which events a change to `event` in `scope` applies to
+    fn series_split(&self, event: &CalendarEvent, scope: RecurrenceScope) -> SeriesSplit {
+        let master = match scope {
+            RecurrenceScope::ThisEvent => None,
+            RecurrenceScope::ThisAndFollowing | RecurrenceScope::AllEvents => {
+                self.get_recurring_event(event)
+            }
+        };
+        let Some(master) = master.cloned() else {
+            return SeriesSplit::Instance;
         };
 
-        Ok(())
-    }
+        // instances that were moved remember where they were in the series
+        let start = event.original_start_time.as_ref().or(event.start.as_ref());
+        let is_first = start.is_some_and(|start| {
+            master.start.as_ref().is_some_and(|master_start| {
+                master_start.date_time == start.date_time && master_start.date == start.date
+            })
+        });
 
-    pub fn get_calendars(&self) -> &[CalendarListEntry] {
-        &self.cache.calendars
+        match (scope, start) {
+            (RecurrenceScope::ThisAndFollowing, Some(start)) if !is_first => {
+                match until_before(start) {
+                    Some(until) => SeriesSplit::At {
+                        master,
+                        start: start.clone(),
+                        until,
+                    },
+                    None => SeriesSplit::Whole(master),
+                }
+            }
+            _ => SeriesSplit::Whole(master),
+        }
     }
 
-    pub fn get_events_by_date(&self, date: &NaiveDate) -> Option<&Vec<CalendarEvent>> {
-        self.cache.events.get(date)
+    /// Counts the occurrences of the series with id `recurring_event_id` before `start`,
+    /// including those that were cancelled
+    fn occurrences_before(&self, recurring_event_id: &str, start: &EventDateTime) -> u32 {
+        let Some(start) = event_date_time_to_local(start) else {
+            return 0;
+        };
+        let is_before = |event: &&CalendarEvent| {
+            event.recurring_event_id.as_deref() == Some(recurring_event_id)
+                && event
+                    .original_start_time
+                    .as_ref()
+                    .or(event.start.as_ref())
+                    .and_then(event_date_time_to_local)
+                    .is_some_and(|event_start| event_start < start)
+        };
+
+        let instances = self
+            .cache
+            .events
+            .values()
+            .flatten()
+            .filter(is_before)
+            .count();
+        let cancelled = self
+            .cache
+            .recurrence_exceptions
+            .values()
+            .filter(|exception| exception.status.as_deref() == Some("cancelled"))
+            .filter(is_before)
+            .count();
+        (instances + cancelled) as u32
     }
+}
 
-    /////////////
-    // Colours //
-    /////////////
+/// Fetches the calendar list, the events in each calendar that changed since the tokens in
+/// `sync_tokens` and `series_sync_tokens` were issued, and the colors
+async fn fetch_sync(
+    hub: Hub,
+    sync_tokens: HashMap<String, String>,
+    series_sync_tokens: HashMap<String, String>,
+    concurrency: usize,
+    progress: UnboundedSender<SyncProgress>,
+) -> Result<SyncedData, TalendarError> {
+    let calendars = hub
+        .calendar_list()
+        .list()
+        .delegate(&mut RetryDelegate::new())
+        .doit()
+        .await?
+        .1 // doit() returns Result<(Response, CalendarList)>
+        .items
+        .unwrap_or_default();
 
-    async fn fetch_colors(&mut self) -> Result<(), google_calendar3::Error>{
-        let (_, colors) = self.hub.colors().get().doit().await?;
-        self.cache.colors = colors;
-        Ok(())
+    let calendar_ids: Vec<(usize, String)> = calendars
+        .iter()
+        .enumerate()
+        .filter_map(|(index, calendar)| Some((index, calendar.id.clone()?)))
+        .collect();
+    let total = calendar_ids.len();
+    let _ = progress.send(SyncProgress { synced: 0, total });
+
+    // the calendars are fetched `concurrency` at a time, each in its own task, and only merged
+    // into the cache once they've all been fetched
+    let mut calendar_ids = calendar_ids.into_iter();
+    let mut tasks = JoinSet::new();
+    let mut changes = Vec::new();
+    let mut failed = Vec::new();
+    loop {
+        while tasks.len() < concurrency.max(1) {
+            let Some((index, calendar_id)) = calendar_ids.next() else {
+                break;
+            };
+            let hub = hub.clone();
+            let sync_token = sync_tokens.get(&calendar_id).cloned();
+            let series_sync_token = series_sync_tokens.get(&calendar_id).cloned();
+            tasks.spawn(async move {
+                (
+                    index,
+                    fetch_calendar_changes(&hub, &calendar_id, sync_token, series_sync_token).await,
+                )
+            });
+        }
+
+        let Some(task) = tasks.join_next().await else {
+            break;
+        };
+        let (index, result) =
+            task.unwrap_or_else(|err| std::panic::resume_unwind(err.into_panic()));
+        // one calendar failing, e.g. a shared calendar we've lost access to, doesn't stop the
+        // others from syncing
+        match result {
+            Ok(calendar_changes) => changes.push((index, calendar_changes)),
+            // without a connection the other calendars would fail too. Returning drops the other
+            // tasks, which cancels them.
+            Err(err @ TalendarError::Network(_)) => return Err(err),
+            Err(error) => {
+                let calendar = &calendars[index];
+                let name = calendar
+                    .summary_override
+                    .as_ref()
+                    .or(calendar.summary.as_ref())
+                    .or(calendar.id.as_ref());
+                failed.push(FailedCalendar {
+                    name: name.cloned().unwrap_or_default(),
+                    error,
+                });
+            }
+        }
+        let _ = progress.send(SyncProgress {
+            synced: changes.len() + failed.len(),
+            total,
+        });
     }
 
+    // merge in the order of the calendar list, whichever order they finished in
+    changes.sort_by_key(|(index, _)| *index);
+    let changes = changes
+        .into_iter()
+        .map(|(_, calendar_changes)| calendar_changes)
+        .collect();
+
+    let (_, colors) = hub
+        .colors()
+        .get()
+        .delegate(&mut RetryDelegate::new())
+        .doit()
+        .await?;
+    Ok(SyncedData {
+        calendars,
+        colors,
+        changes,
+        failed,
+    })
 }
 
+/// Fetches the events in the calendar `calendar_id` that changed since the sync tokens were
+/// issued: expanded into instances, and as the master events of their series
+async fn fetch_calendar_changes(
+    hub: &Hub,
+    calendar_id: &str,
+    sync_token: Option<String>,
+    series_sync_token: Option<String>,
+) -> Result<CalendarChanges, TalendarError> {
+    let (events, sync_token) = list_changed_events(hub, calendar_id, true, sync_token).await?;
+    let (series_events, series_sync_token) =
+        list_changed_events(hub, calendar_id, false, series_sync_token).await?;
+    Ok(CalendarChanges {
+        calendar_id: calendar_id.to_string(),
+        events,
+        sync_token,
+        series_events,
+        series_sync_token,
+    })
+}
+
+/// Fetches every page of the events in the calendar `calendar_id` that changed since
+/// `sync_token` was issued, or all of them if there's no token. Recurring events are expanded
+/// into their instances if `single_events` is set. Returns the events and the next sync token.
+async fn list_changed_events(
+    hub: &Hub,
+    calendar_id: &str,
+    single_events: bool,
+    sync_token: Option<String>,
+) -> Result<(Vec<CalendarEvent>, Option<String>), TalendarError> {
+    let local_timezone = localzone::get_local_zone().unwrap_or("Australia/Melbourne".to_string());
+
+    let local_timezone = local_timezone.as_str();
+    let sync_token = sync_token.as_deref();
+
+    // each page is its own request, and gets its own retries
+    let get_page = |page_token: Option<String>| async move {
+        let mut retry = RetryDelegate::new();
+        let mut request = hub
+            .events()
+            .list(calendar_id)
+            .single_events(single_events)
+            .time_zone(local_timezone)
+            .max_results(2500)
+            .delegate(&mut retry);
+        // use sync token if we have one
+        if let Some(token) = sync_token {
+            request = request.sync_token(token);
+        }
+        if let Some(token) = page_token {
+            request = request.page_token(&token);
+        }
+        request.doit().await
+    };
+
+    let mut events = Vec::new();
+    let mut response = get_page(None).await?.1;
+
+    // loop through all the pages, until we stop receiving events
+    while let Some(page) = response.items.take() {
+        events.extend(page);
 
-pub async fn initialize_calendar_hub(cache_path: &PathBuf) -> CalendarHub<hyper_rustls::HttpsConnector<hyper_util::client::legacy::connect::HttpConnector>> {
+        // handle pagination
+        match response.next_page_token {
+            Some(token) => response = get_page(Some(token)).await?.1,
+            None => break,
+        };
+    }
+
+    Ok((events, response.next_sync_token))
+}
+
+pub async fn initialize_calendar_hub(
+    token_path: &Path,
+    login_flow: LoginFlow,
+) -> Result<Hub, TalendarError> {
     // Read application secret from a file. Sometimes it's easier to compile it directly into
     // the binary. The clientsecret file contains JSON like `{"installed":{"client_id": ... }}`
     let secret = yup_oauth2::read_application_secret("clientsecret.json")
         .await
-        .expect("clientsecret.json");
-
-    // Create an authenticator that uses an InstalledFlow to authenticate. The
-    // authentication tokens are persisted to a file named tokencache.json. The
-    // authenticator takes care of caching tokens to disk and refreshing tokens once
-    // they've expired.
-    let auth = yup_oauth2::InstalledFlowAuthenticator::builder(
-        secret,
-        yup_oauth2::InstalledFlowReturnMethod::HTTPRedirect,
-    )
-        .persist_tokens_to_disk(cache_path.to_str().unwrap().to_owned() + "tokenfile.json")
-        .flow_delegate(Box::new(InstalledFlowBrowserDelegate))
-        .build()
-        .await
-        .expect("Failed to create Authenticator");
+        .map_err(|err| TalendarError::Config(format!(
+            "Couldn't read clientsecret.json ({err}). Download an OAuth client from the Google Cloud \
+             console and save it as clientsecret.json in the directory you run talendar from."
+        )))?;
+
+    // Create an authenticator that uses an InstalledFlow or DeviceFlow to authenticate. The
+    // authentication tokens are persisted to `token_path`, which only its owner can read. The
+    // authenticator takes care of caching tokens to disk and refreshing tokens once they've
+    // expired.
+    restrict_to_owner(token_path);
+    let auth = match login_flow {
+        LoginFlow::Browser {
+            delegate,
+            redirect_port,
+        } => {
+            let return_method = match redirect_port {
+                Some(port) => yup_oauth2::InstalledFlowReturnMethod::HTTPPortRedirect(port),
+                None => yup_oauth2::InstalledFlowReturnMethod::HTTPRedirect,
+            };
+            yup_oauth2::InstalledFlowAuthenticator::builder(secret, return_method)
+                .persist_tokens_to_disk(token_path)
+                .flow_delegate(delegate)
+                .build()
+                .await
+        }
+        LoginFlow::DeviceCode { delegate } => {
+            yup_oauth2::DeviceFlowAuthenticator::builder(secret)
+                .persist_tokens_to_disk(token_path)
+                .flow_delegate(delegate)
+                .build()
+                .await
+        }
+        LoginFlow::ExistingToken => {
+            // the browser flow waits for the redirect even if the delegate fails, but the
+            // interactive flow gives up when the delegate doesn't return a code
+            yup_oauth2::InstalledFlowAuthenticator::builder(
+                secret,
+                yup_oauth2::InstalledFlowReturnMethod::Interactive,
+            )
+            .persist_tokens_to_disk(token_path)
+            .flow_delegate(Box::new(NoLoginDelegate))
+            .build()
+            .await
+        }
+    }
+    .map_err(|err| TalendarError::Auth(Box::new(err)))?;
 
     // It seems that rustls have changed their crypto provider api to support different
     // providers and use aws_lc_rs by default, but hyper_rustls has not been updated to reflect
     // this change. Installing the aws_lc_rs provider explicitly prevents a panic, and it's
     // what should happen automatically anyway (I think).
     let _ = rustls::crypto::aws_lc_rs::default_provider().install_default();
-    
-    let client = hyper_util::client::legacy::Client::builder(
-        hyper_util::rt::TokioExecutor::new()
-    )
+
+    let client = hyper_util::client::legacy::Client::builder(hyper_util::rt::TokioExecutor::new())
         .build(
             hyper_rustls::HttpsConnectorBuilder::new()
                 .with_native_roots()
-                .unwrap()
+                .map_err(|err| TalendarError::Network(Box::new(err)))?
                 .https_or_http()
                 .enable_http1()
-                .build()
+                .build(),
         );
-    let hub = CalendarHub::new(client, auth);
+    Ok(CalendarHub::new(client, auth))
+}
 
-    hub
+/// The events of a recurring series that a change applies to
+enum SeriesSplit {
+    /// just the one event
+    Instance,
+    /// every event in the series with this master event
+    Whole(CalendarEvent),
+    /// the events in the series with this master event from `start` on. The series before them
+    /// ends at `until`.
+    At {
+        master: CalendarEvent,
+        start: EventDateTime,
+        until: String,
+    },
+}
+
+/// Returns `master_time` moved by as much as `changed_time` differs from `original_time`. If
+/// they can't be compared, e.g. because an event changed to all day, `changed_time` is used.
+fn shifted_event_date_time(
+    master_time: Option<&EventDateTime>,
+    original_time: Option<&EventDateTime>,
+    changed_time: Option<&EventDateTime>,
+) -> Option<EventDateTime> {
+    let (Some(master_time), Some(original_time), Some(changed_time)) =
+        (master_time, original_time, changed_time)
+    else {
+        return changed_time.cloned();
+    };
+
+    match (master_time, original_time, changed_time) {
+        (
+            EventDateTime {
+                date_time: Some(master),
+                ..
+            },
+            EventDateTime {
+                date_time: Some(original),
+                ..
+            },
+            EventDateTime {
+                date_time: Some(changed),
+                ..
+            },
+        ) => Some(EventDateTime {
+            date_time: Some(*master + (*changed - *original)),
+            ..master_time.clone()
+        }),
+        (
+            EventDateTime {
+                date: Some(master), ..
+            },
+            EventDateTime {
+                date: Some(original),
+                ..
+            },
+            EventDateTime {
+                date: Some(changed),
+                ..
+            },
+        ) => Some(EventDateTime {
+            date: master.checked_add_signed(*changed - *original),
+            ..master_time.clone()
+        }),
+        _ => Some(EventDateTime {
+            time_zone: master_time.time_zone.clone(),
+            ..changed_time.clone()
+        }),
+    }
+}
+
+/// Returns when `event_date_time` is in local time. All day events start at local midnight.
+fn event_date_time_to_local(event_date_time: &EventDateTime) -> Option<DateTime<Local>> {
+    match event_date_time.date_time {
+        Some(date_time) => Some(date_time.with_timezone(&Local)),
+        None => event_date_time
+            .date?
+            .and_hms_opt(0, 0, 0)?
+            .and_local_timezone(Local)
+            .earliest(),
+    }
+}
+
+/// Our possible responses to an invitation
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ResponseStatus {
+    Accepted,
+    Declined,
+    Tentative,
+}
+
+impl ResponseStatus {
+    /// The value of `responseStatus` used by the API
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            ResponseStatus::Accepted => "accepted",
+            ResponseStatus::Declined => "declined",
+            ResponseStatus::Tentative => "tentative",
+        }
+    }
 }
 
 pub trait CalendarEventExt {
     fn is_multiday(&self) -> bool;
 
+    fn self_response_status(&self) -> Option<&str>;
+
+    fn is_pending_invite(&self) -> bool;
+
+    fn is_declined(&self) -> bool;
+
+    fn is_tentative(&self) -> bool;
+
+    fn is_all_day(&self) -> bool;
+
+    fn is_past(&self, now: DateTime<Local>) -> bool;
+
+    fn get_local_bounds(&self) -> Option<(DateTime<Local>, DateTime<Local>)>;
+
     fn get_naive_date(&self) -> Option<NaiveDate>;
 
     fn get_naive_end_date(&self) -> Option<NaiveDate>;
 
-    fn get_start_string(&self) -> String;
+    fn get_start_string(&self, locale: Locale) -> String;
 }
 
 impl CalendarEventExt for CalendarEvent {
     /// Returns `true` if `self` has both a start and end date, and the number of days in the
     /// difference is greater than one. Returns `false` otherwise.
     fn is_multiday(&self) -> bool {
-        if let ( Some(start_date), Some(end_date) ) = ( self.get_naive_date(),  self.get_naive_end_date() ) {
-            return (end_date - start_date).num_days() <= 1
+        if let (Some(start_date), Some(end_date)) =
+            (self.get_naive_date(), self.get_naive_end_date())
+        {
+            (end_date - start_date).num_days() <= 1
         } else {
-            return false
+            false
         }
     }
 
+    /// Returns our `responseStatus` if we're one of the event's attendees
+    fn self_response_status(&self) -> Option<&str> {
+        self.attendees
+            .as_ref()?
+            .iter()
+            .find(|attendee| attendee.self_ == Some(true))?
+            .response_status
+            .as_deref()
+    }
+
+    /// Returns `true` if we've been invited to the event and haven't responded yet
+    fn is_pending_invite(&self) -> bool {
+        self.self_response_status() == Some("needsAction")
+    }
+
+    /// Returns `true` if we've declined the invitation to the event
+    fn is_declined(&self) -> bool {
+        self.self_response_status() == Some("declined")
+    }
+
+    /// Returns `true` if we've said we might go to the event
+    fn is_tentative(&self) -> bool {
+        self.self_response_status() == Some("tentative")
+    }
+
+    /// Returns `true` if the event has a start date but no start time
+    fn is_all_day(&self) -> bool {
+        self.start
+            .as_ref()
+            .is_some_and(|start| start.date_time.is_none())
+    }
+
+    /// Returns `true` if the event has ended by `now`
+    fn is_past(&self, now: DateTime<Local>) -> bool {
+        self.get_local_bounds().is_some_and(|(_, end)| end <= now)
+    }
+
+    /// Returns when the event starts and ends in local time. All day events start and end at
+    /// local midnight.
+    fn get_local_bounds(&self) -> Option<(DateTime<Local>, DateTime<Local>)> {
+        Some((
+            event_date_time_to_local(self.start.as_ref()?)?,
+            event_date_time_to_local(self.end.as_ref()?)?,
+        ))
+    }
+
     fn get_naive_date(&self) -> Option<NaiveDate> {
-        match &self.start {
-            Some(event_date_time) => {
-                if let Some(date) = event_date_time.date {
-                    Some(date)
-                } else if let Some(date_time) = event_date_time.date_time {
-                    Some(date_time.with_timezone(&Local).date_naive())
-                } else {
-                    None
-                }
-            }
-            None => None
-        }
+        self.start.as_ref().and_then(|event_date_time| {
+            event_date_time.date.or_else(|| {
+                event_date_time
+                    .date_time
+                    .map(|date_time| date_time.with_timezone(&Local).date_naive())
+            })
+        })
     }
 
     fn get_naive_end_date(&self) -> Option<NaiveDate> {
-        match &self.end {
-            Some(event_date_time) => {
-                if let Some(date) = event_date_time.date {
-                    Some(date)
-                } else if let Some(date_time) = event_date_time.date_time {
-                    Some(date_time.naive_local().date())
-                } else {
-                    None
-                }
-            }
-            None => None
-        }
+        self.end.as_ref().and_then(|event_date_time| {
+            event_date_time.date.or_else(|| {
+                event_date_time
+                    .date_time
+                    .map(|date_time| date_time.with_timezone(&Local).date_naive())
+            })
+        })
     }
 
     /// Get the start time of the event. If the event all-day there will be no start time, and the
-    /// result will have "ALL DAY" appended. If the event is not all day then the string
-    /// representation its date_time will be returned
-    fn get_start_string(&self) -> String {
+    /// result will have "ALL DAY" appended. If the event is not all day then its date and time
+    /// will be returned, formatted for `locale`
+    fn get_start_string(&self, locale: Locale) -> String {
         match &self.start {
             Some(event_date_time) => {
                 // First check in date_time as it contains more info than date
                 if let Some(date_time) = event_date_time.date_time {
-                    date_time.with_timezone(&Local).to_string()
+                    let date_time = date_time.with_timezone(&Local);
+                    format!(
+                        "{} {}",
+                        date_string(date_time.date_naive(), locale),
+                        time_string(&date_time, locale)
+                    )
                 }
                 // if date_time is empty, then check for a date
                 else if let Some(date) = event_date_time.date {
-                    date.to_string() + " ALL DAY"
+                    date_string(date, locale) + " ALL DAY"
                 }
                 // if all else fails, return some informative default
-                else{
+                else {
                     String::from("No Start Time")
                 }
             }
-            None => {
-                String::from("No Start Time")
-            }
+            None => String::from("No Start Time"),
         }
     }
 }
 
-/// Returns the NaiveDate of event, or None if event.date does not contain a NaiveDate 
-fn get_event_naive_date(event: &CalendarEvent) -> Option<NaiveDate> {
-    match &event.start {
-        Some(event_date_time) => {
-            if let Some(date) = event_date_time.date {
-                Some(date)
-            } else if let Some(date_time) = event_date_time.date_time {
-                Some(date_time.naive_local().date())
-            } else {
-                None
-            }
-        }
-        None => None
-    }
+/// The order events are listed in on a day: all day events first, then by start time
+fn event_order(event: &CalendarEvent) -> (bool, Option<DateTime<Utc>>) {
+    let start = event.start.as_ref().and_then(|start| start.date_time);
+    (start.is_some(), start)
 }
 
 ////////////////////////////////////////////////////////////
@@ -393,23 +1596,94 @@ impl InstalledFlowDelegate for InstalledFlowBrowserDelegate {
     }
 }
 
+/// Refuses to log in, for when only an existing token should be used
+#[derive(Copy, Clone)]
+struct NoLoginDelegate;
 
+impl InstalledFlowDelegate for NoLoginDelegate {
+    fn present_user_url<'a>(
+        &'a self,
+        _url: &'a str,
+        _need_code: bool,
+    ) -> Pin<Box<dyn Future<Output = Result<String, String>> + Send + 'a>> {
+        Box::pin(async { Err(String::from("Not logged in")) })
+    }
+}
 
 ///////////////////////////////////////////
 //                                       //
 // Custom ratatui InstalledFlowDelegate  //
 //                                       //
 ///////////////////////////////////////////
-#[derive(Copy, Clone)]
-struct InstalledFlowBrowserDelegateRatatui;
 
-impl InstalledFlowDelegate for InstalledFlowBrowserDelegateRatatui {
+/// What the login screen should show the user so they can log in
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LoginPrompt {
+    /// the url to log in at in the browser
+    Url(String),
+    /// the code to enter at `verification_url`, on any device
+    DeviceCode {
+        user_code: String,
+        verification_url: String,
+    },
+}
 
+/// Opens the login url in the browser and sends it to the login screen, which shows it for when
+/// the browser doesn't open. The login itself finishes when Google redirects the browser to our
+/// loopback server, so there's no code for the user to enter.
+#[derive(Clone)]
+pub struct InstalledFlowBrowserDelegateRatatui {
+    prompt_sender: tokio::sync::mpsc::UnboundedSender<LoginPrompt>,
+}
+
+impl InstalledFlowBrowserDelegateRatatui {
+    pub fn new(prompt_sender: tokio::sync::mpsc::UnboundedSender<LoginPrompt>) -> Self {
+        InstalledFlowBrowserDelegateRatatui { prompt_sender }
+    }
+}
+
+impl InstalledFlowDelegate for InstalledFlowBrowserDelegateRatatui {
     fn present_user_url<'a>(
-            &'a self,
-            url: &'a str,
-            need_code: bool,
-        ) -> Pin<Box<dyn Future<Output = Result<String, String>> + Send + 'a>> {
-        todo!()
+        &'a self,
+        url: &'a str,
+        _need_code: bool,
+    ) -> Pin<Box<dyn Future<Output = Result<String, String>> + Send + 'a>> {
+        Box::pin(async move {
+            // the url is shown either way, so it doesn't matter if the browser didn't open
+            _ = webbrowser::open(url);
+            self.prompt_sender
+                .send(LoginPrompt::Url(url.to_string()))
+                .map_err(|err| err.to_string())?;
+            Ok(String::new())
+        })
+    }
+}
+
+/// Sends the user code and verification url to the login screen. The authenticator polls Google
+/// until the user has entered the code, so nothing comes back from the screen.
+#[derive(Clone)]
+pub struct DeviceFlowDelegateRatatui {
+    prompt_sender: tokio::sync::mpsc::UnboundedSender<LoginPrompt>,
+}
+
+impl DeviceFlowDelegateRatatui {
+    pub fn new(prompt_sender: tokio::sync::mpsc::UnboundedSender<LoginPrompt>) -> Self {
+        DeviceFlowDelegateRatatui { prompt_sender }
+    }
+}
+
+impl DeviceFlowDelegate for DeviceFlowDelegateRatatui {
+    fn present_user_code<'a>(
+        &'a self,
+        device_auth_resp: &'a DeviceAuthResponse,
+    ) -> Pin<Box<dyn Future<Output = ()> + Send + 'a>> {
+        Box::pin(async move {
+            // the login screen only goes away once the login has finished, so it's always there
+            // to receive this
+            _ = self.prompt_sender.send(LoginPrompt::DeviceCode {
+                user_code: device_auth_resp.user_code.clone(),
+                verification_url: device_auth_resp.verification_uri.clone(),
+            });
+        })
     }
 }
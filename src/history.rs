@@ -0,0 +1,90 @@
+use google_calendar3::api::Event as CalendarEvent;
+
+use crate::error::TalendarError;
+use crate::google_cal_backend::CalendarClient;
+use crate::recurrence::RecurrenceScope;
+
+/// A change to one event that has been made, with the event as it was before and after it, so
+/// that it can be undone and redone
+#[derive(Debug, Clone)]
+pub struct AppliedChange {
+    pub calendar_id: String,
+    /// the event before the change, or `None` if the change created it
+    pub before: Option<CalendarEvent>,
+    /// the event after the change, or `None` if the change deleted it
+    pub after: Option<CalendarEvent>,
+}
+
+impl AppliedChange {
+    /// The title of the changed event
+    pub fn summary(&self) -> &str {
+        self.after
+            .as_ref()
+            .or(self.before.as_ref())
+            .and_then(|event| event.summary.as_deref())
+            .unwrap_or_default()
+    }
+
+    /// Puts the event back the way it was before the change
+    pub async fn undo(&self, calendar_client: &mut CalendarClient) -> Result<(), TalendarError> {
+        set_event(
+            calendar_client,
+            &self.calendar_id,
+            self.after.as_ref(),
+            self.before.as_ref(),
+        )
+        .await
+    }
+
+    /// Makes the change again, after it was undone
+    pub async fn redo(&self, calendar_client: &mut CalendarClient) -> Result<(), TalendarError> {
+        set_event(
+            calendar_client,
+            &self.calendar_id,
+            self.before.as_ref(),
+            self.after.as_ref(),
+        )
+        .await
+    }
+}
+
+/// The changes made since talendar started, newest last, and the ones that were undone since the
+/// last change, most recently undone last
+#[derive(Debug, Default)]
+pub struct History {
+    pub undo: Vec<AppliedChange>,
+    pub redo: Vec<AppliedChange>,
+}
+
+impl History {
+    /// Keeps a change that was just made. Changes that were undone before it can't be redone
+    /// anymore.
+    pub fn record(&mut self, change: AppliedChange) {
+        self.undo.push(change);
+        self.redo.clear();
+    }
+
+    /// Forgets every change, for after one that can't be undone
+    pub fn clear(&mut self) {
+        self.undo.clear();
+        self.redo.clear();
+    }
+}
+
+/// Changes the event from `from` to `to`, where `None` means the event doesn't exist
+async fn set_event(
+    calendar_client: &mut CalendarClient,
+    calendar_id: &str,
+    from: Option<&CalendarEvent>,
+    to: Option<&CalendarEvent>,
+) -> Result<(), TalendarError> {
+    match (from, to) {
+        (_, Some(to)) => calendar_client.restore_event(calendar_id, to).await,
+        (Some(from), None) => {
+            calendar_client
+                .delete_event(from, RecurrenceScope::ThisEvent)
+                .await
+        }
+        (None, None) => Ok(()),
+    }
+}
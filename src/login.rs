@@ -0,0 +1,382 @@
+use std::{error::Error, time::Duration};
+
+use color_eyre::Result;
+use crossterm::event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers};
+use google_calendar3::yup_oauth2::{self, error::AuthErrorCode};
+use qrcode::{render::unicode::Dense1x2, EcLevel, QrCode};
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    style::{Color, Style},
+    text::{Line, Text},
+    widgets::{Clear, Paragraph, Wrap},
+    DefaultTerminal, Frame,
+};
+use serde::Deserialize;
+use tokio::sync::mpsc;
+
+use crate::app::{determine_cache_path, determine_token_path};
+use crate::config::Config;
+use crate::error::is_network_error;
+use crate::google_cal_backend::{
+    CalendarClient, DeviceFlowDelegateRatatui, InstalledFlowBrowserDelegateRatatui, LoginFlow,
+    LoginPrompt,
+};
+use crate::theme::Theme;
+use crate::utils::SPINNER_FRAMES;
+
+/// How often the spinner moves, and how often we check whether the user cancelled
+const SPINNER_INTERVAL: Duration = Duration::from_millis(100);
+
+/// How to log in to Google, chosen with `login_method` in the config
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LoginMethod {
+    /// a code when there's no display to open a browser on, otherwise the browser
+    #[default]
+    Auto,
+    /// open a link in the browser on this computer
+    Browser,
+    /// enter a code at a link on any other device, like a phone
+    Device,
+}
+
+impl LoginMethod {
+    /// Resolves `Auto` to the browser or a code, depending on whether there's a display
+    pub fn resolve(self) -> Self {
+        match self {
+            LoginMethod::Auto if has_display() => LoginMethod::Browser,
+            LoginMethod::Auto => LoginMethod::Device,
+            method => method,
+        }
+    }
+}
+
+/// Guesses whether a browser opened from here would be seen by the user. Over ssh it would open
+/// on the remote machine if at all, and on Linux and BSD there's nothing to open it on without X
+/// or Wayland.
+fn has_display() -> bool {
+    let is_set = |var: &str| std::env::var_os(var).is_some_and(|value| !value.is_empty());
+    if is_set("SSH_CONNECTION") || is_set("SSH_TTY") {
+        return false;
+    }
+    cfg!(any(target_os = "macos", target_os = "windows"))
+        || is_set("DISPLAY")
+        || is_set("WAYLAND_DISPLAY")
+}
+
+/// The login flow for the command line, which prints the link or code to stdout
+pub fn printed_login_flow(config: &Config) -> LoginFlow {
+    match config.login_method.resolve() {
+        LoginMethod::Device => LoginFlow::device_code(),
+        _ => LoginFlow::browser(config.login_port),
+    }
+}
+
+/// How the login is going
+enum LoginStatus {
+    /// waiting for the user to log in in the browser or enter the code
+    Waiting,
+    /// the login failed or was cancelled, and the user can try again
+    Failed(String),
+}
+
+/// The screen shown while logging in to Google
+struct LoginScreen {
+    /// the url the user needs to open, or the code they need to enter, once we know it
+    prompt: Option<LoginPrompt>,
+    /// the url as a QR code, one string per row
+    qr_code: Option<Vec<String>>,
+    spinner_frame: usize,
+    status: LoginStatus,
+}
+
+impl LoginScreen {
+    fn new() -> Self {
+        LoginScreen {
+            prompt: None,
+            qr_code: None,
+            spinner_frame: 0,
+            status: LoginStatus::Waiting,
+        }
+    }
+
+    fn set_prompt(&mut self, prompt: LoginPrompt) {
+        let url = match &prompt {
+            LoginPrompt::Url(url) => url,
+            LoginPrompt::DeviceCode {
+                verification_url, ..
+            } => verification_url,
+        };
+        // the lowest error correction keeps the code small enough to fit in a terminal
+        self.qr_code = QrCode::with_error_correction_level(url, EcLevel::L)
+            .ok()
+            .map(|qr_code| {
+                qr_code
+                    .render::<Dense1x2>()
+                    .quiet_zone(true)
+                    .build()
+                    .lines()
+                    .map(String::from)
+                    .collect()
+            });
+        self.prompt = Some(prompt);
+    }
+
+    /// Forgets the link or code, which can't be used again after a failed login
+    fn clear_prompt(&mut self) {
+        self.prompt = None;
+        self.qr_code = None;
+    }
+
+    fn draw(&self, frame: &mut Frame, theme: &Theme) {
+        let area = frame.area();
+        frame.render_widget(Clear, area);
+
+        let block = theme.popup_block().title("Log in to Google Calendar");
+        let controls = match self.status {
+            LoginStatus::Waiting => "[Esc] cancel",
+            LoginStatus::Failed(_) => "[r] try again  [q] quit",
+        };
+        let block = block.title_bottom(controls);
+        let inner = block.inner(area);
+        frame.render_widget(block, area);
+
+        let mut lines = Vec::new();
+        match &self.status {
+            LoginStatus::Waiting => {
+                match &self.prompt {
+                    None => {
+                        lines.push(Line::from(format!("{} Signing in...", self.spinner())));
+                    }
+                    Some(LoginPrompt::Url(url)) => {
+                        lines.push(Line::from("Open this link to log in. It should have opened in your browser already."));
+                        lines.push(Line::default());
+                        lines.push(Line::styled(url.as_str(), theme.header));
+                        lines.push(Line::default());
+                        lines.push(Line::from(format!(
+                            "{} Waiting for you to log in in the browser...",
+                            self.spinner()
+                        )));
+                    }
+                    Some(LoginPrompt::DeviceCode {
+                        user_code,
+                        verification_url,
+                    }) => {
+                        lines.push(Line::from(
+                            "On your phone or another computer, open this link:",
+                        ));
+                        lines.push(Line::default());
+                        lines.push(Line::styled(verification_url.as_str(), theme.header));
+                        lines.push(Line::default());
+                        lines.push(Line::from("and enter the code"));
+                        lines.push(Line::default());
+                        lines.push(Line::styled(user_code.as_str(), theme.header));
+                        lines.push(Line::default());
+                        lines.push(Line::from(format!(
+                            "{} Waiting for you to enter the code...",
+                            self.spinner()
+                        )));
+                    }
+                }
+            }
+            LoginStatus::Failed(message) => {
+                lines.push(Line::styled("Login failed", theme.header));
+                lines.push(Line::default());
+                lines.push(Line::from(message.as_str()));
+            }
+        }
+
+        // each line takes as many rows as it wraps onto
+        let text_height = lines
+            .iter()
+            .map(|line| line.width().max(1).div_ceil(inner.width.max(1) as usize) as u16)
+            .sum();
+        let paragraph = Paragraph::new(lines).wrap(Wrap { trim: false });
+        let [text_area, qr_area] =
+            Layout::vertical([Constraint::Length(text_height), Constraint::Fill(1)])
+                .spacing(1)
+                .areas(inner);
+        frame.render_widget(paragraph, text_area);
+
+        if let (LoginStatus::Waiting, Some(qr_code)) = (&self.status, &self.qr_code) {
+            self.draw_qr_code(frame, qr_code, qr_area, theme);
+        }
+    }
+
+    /// Draws the QR code in the middle of `area`, or says how to make room for it if it doesn't fit
+    fn draw_qr_code(&self, frame: &mut Frame, qr_code: &[String], area: Rect, theme: &Theme) {
+        let width = qr_code
+            .first()
+            .map(|row| row.chars().count())
+            .unwrap_or_default() as u16;
+        let height = qr_code.len() as u16;
+        if width > area.width || height > area.height {
+            let hint = format!(
+                "Make the terminal at least {width}x{} to scan the link from your phone",
+                height + 8
+            );
+            frame.render_widget(Line::styled(hint, theme.secondary), area);
+            return;
+        }
+
+        let [area] = Layout::horizontal([Constraint::Length(width)])
+            .flex(Flex::Center)
+            .areas(area);
+        let [area] = Layout::vertical([Constraint::Length(height)]).areas(area);
+        // QR codes need dark modules on a light background, whatever the terminal's colors
+        let text = Text::from_iter(qr_code.iter().map(String::as_str))
+            .style(Style::default().fg(Color::Black).bg(Color::White));
+        frame.render_widget(text, area);
+    }
+
+    fn spinner(&self) -> &'static str {
+        SPINNER_FRAMES[self.spinner_frame % SPINNER_FRAMES.len()]
+    }
+}
+
+/// How logging in ended
+pub enum LoginOutcome {
+    /// we have a token, or just logged in for one
+    LoggedIn(CalendarClient),
+    /// we have a token that needs refreshing, but Google can't be reached to refresh it, so all we
+    /// can show is the cache
+    Offline(CalendarClient),
+    /// the user gave up on logging in
+    Cancelled,
+}
+
+/// Connects to the calendar api, logging in inside the terminal if we don't have a token yet
+pub async fn login(
+    terminal: &mut DefaultTerminal,
+    config: &Config,
+    theme: &Theme,
+) -> Result<LoginOutcome> {
+    let (prompt_sender, mut prompt_receiver) = mpsc::unbounded_channel();
+    let login_method = config.login_method.resolve();
+    let login_flow = match login_method {
+        LoginMethod::Device => LoginFlow::DeviceCode {
+            delegate: Box::new(DeviceFlowDelegateRatatui::new(prompt_sender)),
+        },
+        _ => LoginFlow::Browser {
+            delegate: Box::new(InstalledFlowBrowserDelegateRatatui::new(prompt_sender)),
+            redirect_port: config.login_port,
+        },
+    };
+    let token_path = determine_token_path()?;
+    let calendar_client =
+        CalendarClient::new(determine_cache_path()?, &token_path, login_flow).await?;
+
+    let mut screen = LoginScreen::new();
+    loop {
+        let result = {
+            let authentication = calendar_client.authenticate();
+            tokio::pin!(authentication);
+            loop {
+                tokio::select! {
+                    result = &mut authentication => break Some(result),
+                    Some(prompt) = prompt_receiver.recv() => {
+                        screen.set_prompt(prompt);
+                        terminal.draw(|frame| screen.draw(frame, theme))?;
+                    }
+                    // the screen isn't drawn until the first tick, so that it doesn't flash up
+                    // when we already have a token
+                    _ = tokio::time::sleep(SPINNER_INTERVAL) => {
+                        screen.spinner_frame += 1;
+                        terminal.draw(|frame| screen.draw(frame, theme))?;
+                        if was_cancelled()? {
+                            break None
+                        }
+                    }
+                }
+            }
+        };
+
+        screen.status = match result {
+            Some(Ok(())) => return Ok(LoginOutcome::LoggedIn(calendar_client)),
+            // with a token we've logged in before, so the cache is worth showing until Google can
+            // be reached again
+            Some(Err(err)) if is_network_error(err.as_ref()) && token_path.exists() => {
+                return Ok(LoginOutcome::Offline(calendar_client))
+            }
+            Some(Err(err)) => LoginStatus::Failed(login_error_message(
+                err.as_ref(),
+                login_method,
+                config.login_port,
+            )),
+            None => LoginStatus::Failed(String::from("You cancelled the login.")),
+        };
+        screen.clear_prompt();
+
+        // wait for the user to try again or give up
+        loop {
+            terminal.draw(|frame| screen.draw(frame, theme))?;
+            if let Event::Key(key) = event::read()? {
+                match key.code {
+                    _ if is_quit_key(key) => return Ok(LoginOutcome::Cancelled),
+                    KeyCode::Char('r') | KeyCode::Enter => break,
+                    _ => {}
+                }
+            }
+        }
+        screen.status = LoginStatus::Waiting;
+    }
+}
+
+/// Checks for key presses without waiting, returning `true` if the user wants to stop logging in
+fn was_cancelled() -> Result<bool> {
+    while event::poll(Duration::ZERO)? {
+        if let Event::Key(key) = event::read()? {
+            if is_quit_key(key) {
+                return Ok(true);
+            }
+        }
+    }
+    Ok(false)
+}
+
+fn is_quit_key(key: KeyEvent) -> bool {
+    key.kind == KeyEventKind::Press
+        && (matches!(key.code, KeyCode::Esc | KeyCode::Char('q'))
+            || (key.modifiers == KeyModifiers::CONTROL
+                && matches!(key.code, KeyCode::Char('c') | KeyCode::Char('C'))))
+}
+
+/// Explains why the login failed, with what to do about it where we know
+fn login_error_message(
+    err: &(dyn Error + Send + Sync + 'static),
+    login_method: LoginMethod,
+    login_port: Option<u16>,
+) -> String {
+    let Some(err) = err.downcast_ref::<yup_oauth2::Error>() else {
+        return err.to_string();
+    };
+
+    match err {
+        yup_oauth2::Error::LowLevelError(io_error) if io_error.kind() == std::io::ErrorKind::AddrInUse => {
+            let port = login_port.map(|port| format!("Port {port}")).unwrap_or(String::from("The login port"));
+            format!(
+                "{port} is already in use, so the browser can't send the login back to talendar. \
+                 Close whatever is using it, or set login_port in the config to a free port."
+            )
+        }
+        yup_oauth2::Error::AuthError(auth_error) if auth_error.error == AuthErrorCode::AccessDenied => {
+            String::from("Access was denied. Log in again and allow talendar to see and edit your calendars.")
+        }
+        yup_oauth2::Error::AuthError(auth_error) if auth_error.error == AuthErrorCode::ExpiredToken => {
+            String::from("The code expired before it was entered. Try again for a new one.")
+        }
+        yup_oauth2::Error::AuthError(auth_error)
+            if login_method == LoginMethod::Device
+                && matches!(auth_error.error, AuthErrorCode::InvalidClient | AuthErrorCode::UnauthorizedClient) =>
+        {
+            String::from(
+                "This client can't log in with a code. Logging in with a code needs a clientsecret.json for a \
+                 \"TVs and Limited Input devices\" client, or set login_method = \"browser\" in the config."
+            )
+        }
+        yup_oauth2::Error::HttpError(_) | yup_oauth2::Error::HttpClientError(_) => {
+            format!("Couldn't reach Google. Check your internet connection. ({err})")
+        }
+        err => err.to_string(),
+    }
+}
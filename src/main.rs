@@ -1,24 +1,98 @@
 // TODO Remove this and fix everything
 #![allow(deprecated)]
 
+use clap::Parser;
+use crossterm::event::{DisableMouseCapture, EnableMouseCapture};
+use ratatui::DefaultTerminal;
+
 pub use app::App;
+use cli::Cli;
+use config::Config;
+use error::TalendarError;
+use google_cal_backend::CalendarClient;
+use login::{login, LoginOutcome};
+use theme::Theme;
 
 pub mod app;
-pub mod google_cal_backend;
-mod event_widget;
+mod auth;
 mod calendar_day_widget;
-mod utils;
+mod cli;
+mod colors;
+mod compact_layout;
+mod config;
+mod edit_event_form;
+mod error;
+mod error_log_popup;
+mod event_details_popup;
+mod event_widget;
+mod find_time;
+mod form;
+pub mod google_cal_backend;
+mod history;
 mod inspect_day_popup;
-
+mod login;
+mod quick_add;
+mod recurrence;
+mod reminders;
+mod retry;
+mod status;
+mod status_bar;
+mod text_prompt;
+mod theme;
+mod utils;
 
 #[tokio::main]
 async fn main() -> color_eyre::Result<()> {
     color_eyre::install()?;
-    let terminal = ratatui::init();
 
-    let result = App::new().await.run(terminal);
+    let cli = Cli::parse();
+    if let Some(command) = cli.command {
+        return cli::run(command, cli.json).await;
+    }
+
+    let mut terminal = ratatui::init();
+    crossterm::execute!(std::io::stdout(), EnableMouseCapture)?;
+    // ratatui's panic hook leaves raw mode but not mouse capture, which would fill the shell with
+    // mouse escape codes
+    let restore_terminal = std::panic::take_hook();
+    std::panic::set_hook(Box::new(move |info| {
+        _ = crossterm::execute!(std::io::stdout(), DisableMouseCapture);
+        restore_terminal(info);
+    }));
+
+    let (config, config_error) = Config::load_or_default();
+    let theme = Theme::load(config.theme);
+    let result = match login(&mut terminal, &config, &theme).await {
+        Ok(LoginOutcome::LoggedIn(calendar_client)) => {
+            run_app(terminal, calendar_client, config, config_error, false).await
+        }
+        Ok(LoginOutcome::Offline(calendar_client)) => {
+            run_app(terminal, calendar_client, config, config_error, true).await
+        }
+        // the user gave up on logging in
+        Ok(LoginOutcome::Cancelled) => Ok(()),
+        Err(err) => Err(err),
+    };
+    _ = crossterm::execute!(std::io::stdout(), DisableMouseCapture);
     ratatui::restore();
     result
 }
 
-
+/// Runs the calendar until the user quits. `offline` is whether Google couldn't be reached to log
+/// in, in which case the cache is shown until it can be.
+async fn run_app(
+    terminal: DefaultTerminal,
+    calendar_client: CalendarClient,
+    config: Config,
+    config_error: Option<TalendarError>,
+    offline: bool,
+) -> color_eyre::Result<()> {
+    let mut app = App::new(calendar_client, config);
+    if offline {
+        app.start_offline();
+    }
+    if let Some(err) = config_error {
+        app.report_error("Using the default config", err);
+    }
+    app.run(terminal).await
+}
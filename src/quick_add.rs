@@ -0,0 +1,497 @@
+use chrono::{DateTime, Datelike, Days, Local, Locale, NaiveDate, NaiveTime, TimeDelta, Weekday};
+use google_calendar3::api::{Event as CalendarEvent, EventDateTime};
+use ratatui::{
+    style::{Modifier, Style},
+    text::{Line, Span},
+    widgets::{Clear, Paragraph, Wrap},
+    Frame,
+};
+use serde::Deserialize;
+
+use crate::theme::Theme;
+use crate::utils::{date_string, popup_area, time_string};
+
+/// Timed events are this long unless the text says otherwise
+const DEFAULT_DURATION_MINUTES: i64 = 60;
+
+/// How quick add text is turned into an event
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QuickAddParser {
+    /// send the text to Google's quick add, which understands far more than we do
+    #[default]
+    Google,
+    /// parse the text ourselves and create the event from the result
+    Local,
+}
+
+/// When a quick added event happens
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum QuickAddTime {
+    AllDay(NaiveDate),
+    Timed {
+        start: DateTime<Local>,
+        end: DateTime<Local>,
+    },
+}
+
+/// The result of parsing a line like "Lunch with Sam tomorrow 1pm for 1h @Cafe"
+#[derive(Debug, Clone)]
+pub struct QuickAddEvent {
+    pub summary: String,
+    pub time: QuickAddTime,
+    pub location: Option<String>,
+}
+
+impl QuickAddEvent {
+    /// Builds the event to send to the calendar api
+    pub fn to_calendar_event(&self) -> CalendarEvent {
+        let (start, end) = match self.time {
+            QuickAddTime::AllDay(date) => (
+                EventDateTime {
+                    date: Some(date),
+                    ..Default::default()
+                },
+                EventDateTime {
+                    date: date.succ_opt(),
+                    ..Default::default()
+                },
+            ),
+            QuickAddTime::Timed { start, end } => (
+                EventDateTime {
+                    date_time: Some(start.to_utc()),
+                    ..Default::default()
+                },
+                EventDateTime {
+                    date_time: Some(end.to_utc()),
+                    ..Default::default()
+                },
+            ),
+        };
+
+        CalendarEvent {
+            summary: Some(self.summary.clone()),
+            location: self.location.clone(),
+            start: Some(start),
+            end: Some(end),
+            ..Default::default()
+        }
+    }
+}
+
+/// Parses quick add text. Understands:
+/// - a location at the end, after an "@"
+/// - "today", "tomorrow", weekday names (the next one after today) and dates like 2025-01-31
+/// - times like "1pm", "1:30pm", "13:00" and "noon", optionally preceded by "at"
+/// - time ranges like "1pm-2:30pm"
+/// - durations like "for 1h", "for 30m", "for 1h30m" and "for 2 hours"
+///
+/// Everything else becomes the title. Events without a time are all day events, and events
+/// without a date are today.
+pub fn parse_quick_add(text: &str, now: DateTime<Local>) -> Option<QuickAddEvent> {
+    // the location is everything after the last word starting with an "@"
+    let (text, location) = match text.rfind(" @") {
+        Some(at) => (
+            &text[..at],
+            Some(text[at + 2..].trim().to_string()).filter(|location| !location.is_empty()),
+        ),
+        None => (text, None),
+    };
+
+    let words: Vec<&str> = text.split_whitespace().collect();
+    let mut summary_words = Vec::new();
+    let mut date = None;
+    let mut start_time = None;
+    let mut end_time = None;
+    let mut duration = None;
+
+    let mut i = 0;
+    while i < words.len() {
+        let word = words[i].to_lowercase();
+        let next_word = words.get(i + 1).map(|word| word.to_lowercase());
+
+        if let Some(parsed_date) = parse_date(&word, now.date_naive()) {
+            date = Some(parsed_date);
+        } else if let Some((start, end)) = parse_time_range(&word) {
+            (start_time, end_time) = (Some(start), end);
+        } else if let (true, Some(next_word)) = (word == "at" || word == "on", &next_word) {
+            // "at" and "on" are only dropped when they introduce a time or date
+            if parse_time_range(next_word).is_some()
+                || parse_date(next_word, now.date_naive()).is_some()
+            {
+                i += 1;
+                continue;
+            }
+            summary_words.push(words[i]);
+        } else if let (true, Some(next_word)) = (word == "for", &next_word) {
+            // "for 2 hours" is three words, "for 2h" is two
+            let following_word = words
+                .get(i + 2)
+                .map(|word| word.to_lowercase())
+                .unwrap_or_default();
+            if let Some(parsed) = parse_duration(&format!("{next_word}{following_word}")) {
+                duration = Some(parsed);
+                i += 3;
+                continue;
+            } else if let Some(parsed) = parse_duration(next_word) {
+                duration = Some(parsed);
+                i += 2;
+                continue;
+            }
+            summary_words.push(words[i]);
+        } else {
+            summary_words.push(words[i]);
+        }
+        i += 1;
+    }
+
+    let summary = summary_words.join(" ");
+    if summary.is_empty() {
+        return None;
+    }
+
+    let date = date.unwrap_or(now.date_naive());
+    let time = match start_time {
+        None => QuickAddTime::AllDay(date),
+        Some(start_time) => {
+            let start = date
+                .and_time(start_time)
+                .and_local_timezone(Local)
+                .earliest()?;
+            let end = match (end_time, duration) {
+                (Some(end_time), _) => date
+                    .and_time(end_time)
+                    .and_local_timezone(Local)
+                    .earliest()?,
+                (None, Some(duration)) => start + duration,
+                (None, None) => start + TimeDelta::minutes(DEFAULT_DURATION_MINUTES),
+            };
+            QuickAddTime::Timed {
+                start,
+                end: end.max(start),
+            }
+        }
+    };
+
+    Some(QuickAddEvent {
+        summary,
+        time,
+        location,
+    })
+}
+
+/// Parses "today", "tomorrow", a weekday name or a date like 2025-01-31
+pub fn parse_date(word: &str, today: NaiveDate) -> Option<NaiveDate> {
+    match word {
+        "today" => Some(today),
+        "tomorrow" => today.succ_opt(),
+        _ => {
+            if let Ok(weekday) = word.parse::<Weekday>() {
+                // the next one, not counting today
+                let days_until = match weekday.days_since(today.weekday()) {
+                    0 => 7,
+                    days => days,
+                };
+                return today.checked_add_days(Days::new(days_until.into()));
+            }
+            NaiveDate::parse_from_str(word, "%Y-%m-%d").ok()
+        }
+    }
+}
+
+/// Parses a time like "1pm", or a range of times like "1pm-2:30pm"
+fn parse_time_range(word: &str) -> Option<(NaiveTime, Option<NaiveTime>)> {
+    match word.split_once('-') {
+        Some((start, end)) => {
+            let end = parse_time(end)?;
+            // "1-2pm" means 1pm to 2pm
+            let start = parse_time(start)
+                .or_else(|| parse_time(&format!("{start}{}", am_pm_suffix(end))))?;
+            Some((start, Some(end)))
+        }
+        None => Some((parse_time(word)?, None)),
+    }
+}
+
+fn am_pm_suffix(time: NaiveTime) -> &'static str {
+    if time >= NaiveTime::from_hms_opt(12, 0, 0).unwrap_or_default() {
+        "pm"
+    } else {
+        "am"
+    }
+}
+
+/// Parses a time like "1pm", "1:30pm", "13:00" or "noon"
+fn parse_time(word: &str) -> Option<NaiveTime> {
+    match word {
+        "noon" => return NaiveTime::from_hms_opt(12, 0, 0),
+        "midnight" => return NaiveTime::from_hms_opt(0, 0, 0),
+        _ => {}
+    }
+
+    let (digits, pm) = if let Some(digits) = word.strip_suffix("pm") {
+        (digits, Some(true))
+    } else if let Some(digits) = word.strip_suffix("am") {
+        (digits, Some(false))
+    } else {
+        (word, None)
+    };
+
+    let (hour, minute) = match digits.split_once(':') {
+        Some((hour, minute)) => (hour.parse::<u32>().ok()?, minute.parse::<u32>().ok()?),
+        // a bare number is only a time with am or pm, otherwise "2" in "2 tickets" would be a time
+        None if pm.is_some() => (digits.parse::<u32>().ok()?, 0),
+        None => return None,
+    };
+
+    let hour = match pm {
+        Some(_) if !(1..=12).contains(&hour) => return None,
+        Some(true) if hour != 12 => hour + 12,
+        Some(false) if hour == 12 => 0,
+        _ => hour,
+    };
+    NaiveTime::from_hms_opt(hour, minute, 0)
+}
+
+/// Parses a duration like "1h", "30m", "1h30m", "90min", "2hours" or "1.5h"
+fn parse_duration(word: &str) -> Option<TimeDelta> {
+    let mut minutes = 0.0;
+    let mut number = String::new();
+    let mut chars = word.chars().peekable();
+    let mut parsed_anything = false;
+
+    while let Some(c) = chars.next() {
+        if c.is_ascii_digit() || c == '.' {
+            number.push(c);
+            continue;
+        }
+
+        let mut unit = String::from(c);
+        while let Some(c) = chars.next_if(|c| c.is_alphabetic()) {
+            unit.push(c);
+        }
+        let value: f64 = number.parse().ok()?;
+        number.clear();
+        minutes += match unit.as_str() {
+            "h" | "hr" | "hrs" | "hour" | "hours" => value * 60.0,
+            "m" | "min" | "mins" | "minute" | "minutes" => value,
+            _ => return None,
+        };
+        parsed_anything = true;
+    }
+
+    (parsed_anything && number.is_empty() && minutes > 0.0)
+        .then(|| TimeDelta::minutes(minutes as i64))
+}
+
+/// Draws the parsed event so the user can check it before it is created
+pub fn draw_quick_add_preview(
+    frame: &mut Frame,
+    text: &str,
+    event: &QuickAddEvent,
+    parser: QuickAddParser,
+    theme: &Theme,
+    locale: Locale,
+) {
+    let area = popup_area(frame.area(), 60, 40);
+    frame.render_widget(Clear, area); // this clears out the background
+
+    let label_style = Style::default().add_modifier(Modifier::BOLD);
+    let when = match event.time {
+        QuickAddTime::AllDay(date) => format!("{} (all day)", date_string(date, locale)),
+        QuickAddTime::Timed { start, end } => format!(
+            "{} {} - {}",
+            date_string(start.date_naive(), locale),
+            time_string(&start, locale),
+            time_string(&end, locale)
+        ),
+    };
+
+    let mut lines = vec![
+        Line::from(vec![
+            Span::styled("Title: ", label_style),
+            Span::from(event.summary.as_str()),
+        ]),
+        Line::from(vec![Span::styled("When: ", label_style), Span::from(when)]),
+    ];
+    if let Some(location) = &event.location {
+        lines.push(Line::from(vec![
+            Span::styled("Where: ", label_style),
+            Span::from(location.as_str()),
+        ]));
+    }
+    if parser == QuickAddParser::Google {
+        lines.push(Line::default());
+        lines.push(Line::from(format!(
+            "\"{text}\" will be sent to Google, which may read it a little differently"
+        )));
+    }
+
+    let block = theme
+        .popup_block()
+        .title("Quick add")
+        .title_bottom("[Enter] create  [e]dit  [Esc] cancel");
+    let paragraph = Paragraph::new(lines)
+        .block(block)
+        .wrap(Wrap { trim: false });
+    frame.render_widget(paragraph, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+
+    use super::*;
+
+    /// Wednesday 29 January 2025, 9am
+    fn now() -> DateTime<Local> {
+        local(2025, 1, 29, 9, 0)
+    }
+
+    fn local(year: i32, month: u32, day: u32, hour: u32, minute: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(year, month, day, hour, minute, 0)
+            .unwrap()
+    }
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    fn timed(start: DateTime<Local>, end: DateTime<Local>) -> QuickAddTime {
+        QuickAddTime::Timed { start, end }
+    }
+
+    #[test]
+    fn a_title_alone_is_all_day_today() {
+        let event = parse_quick_add("Pay rent", now()).unwrap();
+        assert_eq!(event.summary, "Pay rent");
+        assert_eq!(event.time, QuickAddTime::AllDay(date(2025, 1, 29)));
+        assert_eq!(event.location, None);
+    }
+
+    #[test]
+    fn dates() {
+        let on = |text| parse_quick_add(text, now()).unwrap().time;
+        assert_eq!(on("Gym today"), QuickAddTime::AllDay(date(2025, 1, 29)));
+        assert_eq!(on("Gym tomorrow"), QuickAddTime::AllDay(date(2025, 1, 30)));
+        assert_eq!(on("Gym friday"), QuickAddTime::AllDay(date(2025, 1, 31)));
+        assert_eq!(on("Gym on Monday"), QuickAddTime::AllDay(date(2025, 2, 3)));
+        assert_eq!(on("Gym 2025-03-01"), QuickAddTime::AllDay(date(2025, 3, 1)));
+        // today's weekday means next week's
+        assert_eq!(on("Gym wednesday"), QuickAddTime::AllDay(date(2025, 2, 5)));
+    }
+
+    #[test]
+    fn times_last_an_hour() {
+        let at = |text| parse_quick_add(text, now()).unwrap().time;
+        let today_at = |hour, minute| local(2025, 1, 29, hour, minute);
+        assert_eq!(at("Call 1pm"), timed(today_at(13, 0), today_at(14, 0)));
+        assert_eq!(
+            at("Call at 1:30pm"),
+            timed(today_at(13, 30), today_at(14, 30))
+        );
+        assert_eq!(at("Call 13:00"), timed(today_at(13, 0), today_at(14, 0)));
+        assert_eq!(at("Call noon"), timed(today_at(12, 0), today_at(13, 0)));
+        assert_eq!(at("Call 12am"), timed(today_at(0, 0), today_at(1, 0)));
+    }
+
+    #[test]
+    fn time_ranges() {
+        let at = |text| parse_quick_add(text, now()).unwrap().time;
+        let today_at = |hour, minute| local(2025, 1, 29, hour, minute);
+        assert_eq!(
+            at("Review 1pm-2:30pm"),
+            timed(today_at(13, 0), today_at(14, 30))
+        );
+        // the start takes the end's am or pm
+        assert_eq!(at("Review 1-2pm"), timed(today_at(13, 0), today_at(14, 0)));
+        assert_eq!(
+            at("Review 10-11am"),
+            timed(today_at(10, 0), today_at(11, 0))
+        );
+        assert_eq!(
+            at("Review 11am-1pm"),
+            timed(today_at(11, 0), today_at(13, 0))
+        );
+    }
+
+    #[test]
+    fn durations() {
+        let at = |text| parse_quick_add(text, now()).unwrap().time;
+        let today_at = |hour, minute| local(2025, 1, 29, hour, minute);
+        assert_eq!(
+            at("Standup 9:30am for 15m"),
+            timed(today_at(9, 30), today_at(9, 45))
+        );
+        assert_eq!(
+            at("Workshop 10am for 1h30m"),
+            timed(today_at(10, 0), today_at(11, 30))
+        );
+        assert_eq!(
+            at("Workshop 10am for 2 hours"),
+            timed(today_at(10, 0), today_at(12, 0))
+        );
+        assert_eq!(
+            at("Workshop 10am for 1.5h"),
+            timed(today_at(10, 0), today_at(11, 30))
+        );
+        assert_eq!(
+            at("Workshop 10am for 90min"),
+            timed(today_at(10, 0), today_at(11, 30))
+        );
+    }
+
+    #[test]
+    fn everything_together() {
+        let event =
+            parse_quick_add("Lunch with Sam tomorrow at 1pm for 45m @Cafe Luna", now()).unwrap();
+        assert_eq!(event.summary, "Lunch with Sam");
+        assert_eq!(
+            event.time,
+            timed(local(2025, 1, 30, 13, 0), local(2025, 1, 30, 13, 45))
+        );
+        assert_eq!(event.location.as_deref(), Some("Cafe Luna"));
+    }
+
+    #[test]
+    fn locations() {
+        let location = |text| parse_quick_add(text, now()).unwrap().location;
+        assert_eq!(
+            location("Party @ Sam's place "),
+            Some(String::from("Sam's place"))
+        );
+        assert_eq!(location("Party @"), None);
+        // only the last " @" starts the location
+        assert_eq!(
+            location("Email @sam about @home"),
+            Some(String::from("home"))
+        );
+        assert_eq!(location("Email sam@example.com"), None);
+    }
+
+    #[test]
+    fn words_that_only_look_like_times_stay_in_the_title() {
+        let summary = |text| parse_quick_add(text, now()).unwrap().summary;
+        assert_eq!(summary("Meet at the park"), "Meet at the park");
+        assert_eq!(summary("Buy 2 tickets"), "Buy 2 tickets");
+        assert_eq!(summary("Dinner for two"), "Dinner for two");
+        assert_eq!(summary("Vote on budget"), "Vote on budget");
+    }
+
+    #[test]
+    fn a_title_is_needed() {
+        assert!(parse_quick_add("tomorrow 1pm", now()).is_none());
+        assert!(parse_quick_add("  @Cafe", now()).is_none());
+        assert!(parse_quick_add("", now()).is_none());
+    }
+
+    #[test]
+    fn all_day_events_end_the_next_day() {
+        let event = parse_quick_add("Holiday 2025-03-01", now())
+            .unwrap()
+            .to_calendar_event();
+        assert_eq!(event.start.unwrap().date, Some(date(2025, 3, 1)));
+        assert_eq!(event.end.unwrap().date, Some(date(2025, 3, 2)));
+    }
+}
@@ -0,0 +1,420 @@
+use chrono::{Locale, NaiveDate, NaiveDateTime, TimeDelta, Weekday};
+use google_calendar3::api::EventDateTime;
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    widgets::{Clear, List, ListState},
+    Frame,
+};
+
+use crate::theme::Theme;
+use crate::utils::{date_string, month_name, weekday_name};
+
+/// The day and month names in a description of a series. The description is an English
+/// sentence, like the rest of talendar, so they are English too.
+const DESCRIPTION_LOCALE: Locale = Locale::en_US;
+
+/// Which events of a recurring series a change applies to
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RecurrenceScope {
+    ThisEvent,
+    ThisAndFollowing,
+    AllEvents,
+}
+
+impl RecurrenceScope {
+    pub fn label(&self) -> &'static str {
+        match self {
+            RecurrenceScope::ThisEvent => "This event",
+            RecurrenceScope::ThisAndFollowing => "This and following events",
+            RecurrenceScope::AllEvents => "All events",
+        }
+    }
+}
+
+/// Describes the recurrence rules of a series, e.g. "Every 2 weeks on Monday, Wednesday, until
+/// 01/31/25". Only the first RRULE is described; rules we don't understand are shown as they are.
+/// Only the date of an UNTIL is written in `locale`'s date order.
+pub fn describe_recurrence(rules: &[String], locale: Locale) -> Option<String> {
+    let rule = rules.iter().find_map(|rule| rule.strip_prefix("RRULE:"))?;
+    let parts: Vec<(&str, &str)> = rule
+        .split(';')
+        .filter_map(|part| part.split_once('='))
+        .collect();
+    let get = |name: &str| {
+        parts
+            .iter()
+            .find(|(key, _)| *key == name)
+            .map(|(_, value)| *value)
+    };
+
+    let interval = get("INTERVAL")
+        .and_then(|interval| interval.parse::<u32>().ok())
+        .unwrap_or(1);
+    let unit = match get("FREQ") {
+        Some("DAILY") => "day",
+        Some("WEEKLY") => "week",
+        Some("MONTHLY") => "month",
+        Some("YEARLY") => "year",
+        _ => return Some(rule.to_string()),
+    };
+    let by_day: Vec<(Option<i32>, Weekday)> = get("BYDAY")
+        .map(|days| days.split(',').filter_map(parse_by_day).collect())
+        .unwrap_or_default();
+
+    let every_weekday = interval == 1 && unit == "week" && is_weekdays(&by_day);
+    let mut description = match interval {
+        _ if every_weekday => String::from("Every weekday"),
+        1 => format!("Every {unit}"),
+        interval => format!("Every {interval} {unit}s"),
+    };
+
+    if !by_day.is_empty() && !every_weekday {
+        let days: Vec<String> = by_day
+            .iter()
+            .map(|(ordinal, weekday)| match ordinal {
+                Some(ordinal) => format!(
+                    "the {} {}",
+                    ordinal_string(*ordinal),
+                    weekday_name(*weekday, DESCRIPTION_LOCALE)
+                ),
+                None => weekday_name(*weekday, DESCRIPTION_LOCALE),
+            })
+            .collect();
+        description += &format!(" on {}", days.join(", "));
+    }
+    if let Some(month_days) = get("BYMONTHDAY") {
+        description += &format!(" on day {}", month_days.replace(',', ", "));
+    }
+    if let Some(months) = get("BYMONTH") {
+        let months: Vec<String> = months
+            .split(',')
+            .filter_map(|month| month.parse::<u32>().ok())
+            .map(|month| month_name(month, DESCRIPTION_LOCALE))
+            .collect();
+        description += &format!(" in {}", months.join(", "));
+    }
+
+    if let Some(count) = get("COUNT") {
+        description += &format!(", {count} times");
+    }
+    if let Some(until) = get("UNTIL").and_then(parse_until) {
+        description += &format!(", until {}", date_string(until, locale));
+    }
+
+    Some(description)
+}
+
+/// Parses a BYDAY entry like "MO", "1MO" or "-1FR" into its ordinal and weekday
+fn parse_by_day(day: &str) -> Option<(Option<i32>, Weekday)> {
+    let split = day.len().checked_sub(2)?;
+    let (ordinal, weekday) = day.split_at(split);
+    let weekday = match weekday {
+        "MO" => Weekday::Mon,
+        "TU" => Weekday::Tue,
+        "WE" => Weekday::Wed,
+        "TH" => Weekday::Thu,
+        "FR" => Weekday::Fri,
+        "SA" => Weekday::Sat,
+        "SU" => Weekday::Sun,
+        _ => return None,
+    };
+    let ordinal = if ordinal.is_empty() {
+        None
+    } else {
+        Some(ordinal.trim_start_matches('+').parse().ok()?)
+    };
+    Some((ordinal, weekday))
+}
+
+/// Returns `true` if `by_day` is exactly Monday to Friday
+fn is_weekdays(by_day: &[(Option<i32>, Weekday)]) -> bool {
+    let weekdays = [
+        Weekday::Mon,
+        Weekday::Tue,
+        Weekday::Wed,
+        Weekday::Thu,
+        Weekday::Fri,
+    ];
+    by_day.len() == weekdays.len()
+        && weekdays
+            .iter()
+            .all(|weekday| by_day.contains(&(None, *weekday)))
+}
+
+fn ordinal_string(ordinal: i32) -> String {
+    match ordinal {
+        1 => String::from("first"),
+        2 => String::from("second"),
+        3 => String::from("third"),
+        4 => String::from("fourth"),
+        -1 => String::from("last"),
+        -2 => String::from("second to last"),
+        ordinal => format!("{ordinal}."),
+    }
+}
+
+/// Parses the date of an UNTIL, which is either a date like 20250131 or a UTC date-time like
+/// 20250131T235959Z
+fn parse_until(until: &str) -> Option<NaiveDate> {
+    NaiveDate::parse_from_str(until, "%Y%m%d").ok().or_else(|| {
+        NaiveDateTime::parse_from_str(until, "%Y%m%dT%H%M%SZ")
+            .ok()
+            .map(|until| until.date())
+    })
+}
+
+/// Returns the UNTIL that ends a series just before an occurrence starting at `start`
+pub fn until_before(start: &EventDateTime) -> Option<String> {
+    if let Some(date_time) = start.date_time {
+        return Some(
+            (date_time - TimeDelta::seconds(1))
+                .format("%Y%m%dT%H%M%SZ")
+                .to_string(),
+        );
+    }
+    Some(start.date?.pred_opt()?.format("%Y%m%d").to_string())
+}
+
+/// Returns `rules` with every RRULE ending at `until` instead of when it used to
+pub fn rules_ending_at(rules: &[String], until: &str) -> Vec<String> {
+    rules
+        .iter()
+        .map(|rule| match rule.strip_prefix("RRULE:") {
+            Some(rule) => {
+                let mut parts: Vec<&str> = rule
+                    .split(';')
+                    .filter(|part| !part.starts_with("COUNT=") && !part.starts_with("UNTIL="))
+                    .collect();
+                let until = format!("UNTIL={until}");
+                parts.push(&until);
+                format!("RRULE:{}", parts.join(";"))
+            }
+            None => rule.clone(),
+        })
+        .collect()
+}
+
+/// Returns the rules for the rest of a series that is split after `occurrences_before`
+/// occurrences. Series that end after a number of occurrences have that many fewer.
+pub fn rules_continuing_after(rules: &[String], occurrences_before: u32) -> Vec<String> {
+    rules
+        .iter()
+        .map(|rule| match rule.strip_prefix("RRULE:") {
+            Some(rule) => {
+                let parts: Vec<String> = rule
+                    .split(';')
+                    .map(|part| {
+                        match part
+                            .strip_prefix("COUNT=")
+                            .and_then(|count| count.parse::<u32>().ok())
+                        {
+                            Some(count) => {
+                                format!("COUNT={}", count.saturating_sub(occurrences_before).max(1))
+                            }
+                            None => part.to_string(),
+                        }
+                    })
+                    .collect();
+                format!("RRULE:{}", parts.join(";"))
+            }
+            None => rule.clone(),
+        })
+        .collect()
+}
+
+/////////////////////////
+//                     //
+// Scope chooser       //
+//                     //
+/////////////////////////
+
+/// Asks which events of a series a change should apply to
+pub struct ScopeChooser {
+    pub title: String,
+    pub scopes: Vec<RecurrenceScope>,
+    pub list_state: ListState,
+}
+
+impl ScopeChooser {
+    pub fn new(title: impl Into<String>, scopes: Vec<RecurrenceScope>) -> Self {
+        ScopeChooser {
+            title: title.into(),
+            scopes,
+            list_state: ListState::default().with_selected(Some(0)),
+        }
+    }
+
+    pub fn selected_scope(&self) -> Option<RecurrenceScope> {
+        self.scopes.get(self.list_state.selected()?).copied()
+    }
+}
+
+pub fn draw_scope_chooser(frame: &mut Frame, chooser: &mut ScopeChooser, theme: &Theme) {
+    let vertical =
+        Layout::vertical([Constraint::Length(chooser.scopes.len() as u16 + 2)]).flex(Flex::Center);
+    let horizontal = Layout::horizontal([Constraint::Percentage(50)]).flex(Flex::Center);
+    let [area] = vertical.areas(frame.area());
+    let [area] = horizontal.areas(area);
+    frame.render_widget(Clear, area); // this clears out the background
+
+    let block = theme
+        .popup_block()
+        .title(chooser.title.as_str())
+        .title_bottom("[j/k] move  [Enter] confirm  [Esc] cancel");
+    let list = List::new(chooser.scopes.iter().map(|scope| scope.label()))
+        .block(block)
+        .highlight_style(theme.selection);
+    frame.render_stateful_widget(list, area, &mut chooser.list_state);
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{TimeZone, Utc};
+
+    use super::*;
+
+    fn rules(rules: &[&str]) -> Vec<String> {
+        rules.iter().map(|rule| rule.to_string()).collect()
+    }
+
+    fn describe(rule: &str) -> String {
+        describe_recurrence(&rules(&[rule]), Locale::en_US).unwrap()
+    }
+
+    #[test]
+    fn describes_frequencies_and_intervals() {
+        assert_eq!(describe("RRULE:FREQ=DAILY"), "Every day");
+        assert_eq!(describe("RRULE:FREQ=WEEKLY;INTERVAL=2"), "Every 2 weeks");
+        assert_eq!(describe("RRULE:FREQ=MONTHLY;INTERVAL=1"), "Every month");
+        assert_eq!(describe("RRULE:FREQ=YEARLY;INTERVAL=3"), "Every 3 years");
+    }
+
+    #[test]
+    fn describes_days_and_months() {
+        assert_eq!(
+            describe("RRULE:FREQ=WEEKLY;BYDAY=MO,WE"),
+            "Every week on Monday, Wednesday"
+        );
+        assert_eq!(
+            describe("RRULE:FREQ=WEEKLY;BYDAY=MO,TU,WE,TH,FR"),
+            "Every weekday"
+        );
+        assert_eq!(
+            describe("RRULE:FREQ=WEEKLY;INTERVAL=2;BYDAY=MO,TU,WE,TH,FR"),
+            "Every 2 weeks on Monday, Tuesday, Wednesday, Thursday, Friday"
+        );
+        assert_eq!(
+            describe("RRULE:FREQ=MONTHLY;BYDAY=1TU"),
+            "Every month on the first Tuesday"
+        );
+        assert_eq!(
+            describe("RRULE:FREQ=MONTHLY;BYDAY=-1FR"),
+            "Every month on the last Friday"
+        );
+        assert_eq!(
+            describe("RRULE:FREQ=MONTHLY;BYMONTHDAY=1,15"),
+            "Every month on day 1, 15"
+        );
+        assert_eq!(
+            describe("RRULE:FREQ=YEARLY;BYMONTH=3,9"),
+            "Every year in March, September"
+        );
+    }
+
+    #[test]
+    fn describes_ends() {
+        assert_eq!(describe("RRULE:FREQ=DAILY;COUNT=5"), "Every day, 5 times");
+        assert_eq!(
+            describe("RRULE:FREQ=DAILY;UNTIL=20250131"),
+            "Every day, until 01/31/2025"
+        );
+        assert_eq!(
+            describe("RRULE:FREQ=DAILY;UNTIL=20250131T235959Z"),
+            "Every day, until 01/31/2025"
+        );
+    }
+
+    #[test]
+    fn describes_in_english_with_the_locales_dates() {
+        let rules = rules(&["RRULE:FREQ=WEEKLY;BYDAY=MO;BYMONTH=3;UNTIL=20250131"]);
+        assert_eq!(
+            describe_recurrence(&rules, Locale::de_DE).unwrap(),
+            "Every week on Monday in March, until 31.01.2025"
+        );
+    }
+
+    #[test]
+    fn describes_only_the_rrule() {
+        let rules = rules(&["EXDATE;VALUE=DATE:20250107", "RRULE:FREQ=DAILY"]);
+        assert_eq!(
+            describe_recurrence(&rules, Locale::en_US).unwrap(),
+            "Every day"
+        );
+        assert_eq!(describe_recurrence(&[], Locale::en_US), None);
+        // rules we can't describe are shown as they are
+        assert_eq!(describe("RRULE:FREQ=HOURLY"), "FREQ=HOURLY");
+    }
+
+    #[test]
+    fn until_before_ends_the_day_or_second_before() {
+        let timed = EventDateTime {
+            date_time: Some(Utc.with_ymd_and_hms(2025, 1, 31, 9, 0, 0).unwrap()),
+            ..Default::default()
+        };
+        assert_eq!(until_before(&timed).as_deref(), Some("20250131T085959Z"));
+
+        let all_day = EventDateTime {
+            date: NaiveDate::from_ymd_opt(2025, 3, 1),
+            ..Default::default()
+        };
+        assert_eq!(until_before(&all_day).as_deref(), Some("20250228"));
+    }
+
+    #[test]
+    fn rules_ending_at_replaces_count_and_until() {
+        let until = "20250131T085959Z";
+        assert_eq!(
+            rules_ending_at(&rules(&["RRULE:FREQ=WEEKLY;BYDAY=MO"]), until),
+            rules(&["RRULE:FREQ=WEEKLY;BYDAY=MO;UNTIL=20250131T085959Z"])
+        );
+        assert_eq!(
+            rules_ending_at(&rules(&["RRULE:FREQ=DAILY;COUNT=10"]), until),
+            rules(&["RRULE:FREQ=DAILY;UNTIL=20250131T085959Z"])
+        );
+        assert_eq!(
+            rules_ending_at(&rules(&["RRULE:FREQ=DAILY;UNTIL=20251231"]), until),
+            rules(&["RRULE:FREQ=DAILY;UNTIL=20250131T085959Z"])
+        );
+    }
+
+    #[test]
+    fn rules_ending_at_keeps_other_rules() {
+        let exdate = "EXDATE;TZID=Australia/Melbourne:20250107T090000";
+        assert_eq!(
+            rules_ending_at(&rules(&[exdate, "RRULE:FREQ=DAILY"]), "20250131"),
+            rules(&[exdate, "RRULE:FREQ=DAILY;UNTIL=20250131"])
+        );
+    }
+
+    #[test]
+    fn rules_continuing_after_counts_down() {
+        assert_eq!(
+            rules_continuing_after(&rules(&["RRULE:FREQ=DAILY;COUNT=10"]), 4),
+            rules(&["RRULE:FREQ=DAILY;COUNT=6"])
+        );
+        // there's always at least the event that was split at
+        assert_eq!(
+            rules_continuing_after(&rules(&["RRULE:FREQ=DAILY;COUNT=3"]), 5),
+            rules(&["RRULE:FREQ=DAILY;COUNT=1"])
+        );
+    }
+
+    #[test]
+    fn rules_continuing_after_keeps_series_without_a_count() {
+        let rules = rules(&[
+            "EXDATE;VALUE=DATE:20250107",
+            "RRULE:FREQ=WEEKLY;BYDAY=MO;UNTIL=20251231",
+        ]);
+        assert_eq!(rules_continuing_after(&rules, 4), rules);
+    }
+}
@@ -0,0 +1,138 @@
+use std::io::Write;
+
+use chrono::{DateTime, Days, Local, TimeDelta};
+use google_calendar3::api::Event as CalendarEvent;
+use serde::Deserialize;
+
+use crate::google_cal_backend::{CalendarClient, CalendarEventExt};
+
+/// Reminders can be set at most four weeks before an event, so that's as far ahead as we look
+const REMINDER_LOOKAHEAD_DAYS: u64 = 28;
+
+const DEFAULT_TITLE: &str = "No title";
+
+/// A popup reminder of an event that is due to be shown
+#[derive(Debug, Clone)]
+pub struct Reminder {
+    /// identifies this reminder of this occurrence of the event, so that it can be dismissed
+    pub key: String,
+    pub summary: String,
+    pub event_start: DateTime<Local>,
+}
+
+impl Reminder {
+    /// Describes when the event starts relative to `now`, e.g. "Standup in 10 minutes"
+    pub fn message(&self, now: DateTime<Local>) -> String {
+        let minutes = (self.event_start - now).num_minutes();
+        match minutes {
+            minutes if minutes <= 0 => format!("{} is starting now", self.summary),
+            1 => format!("{} in 1 minute", self.summary),
+            minutes => format!("{} in {} minutes", self.summary, minutes),
+        }
+    }
+}
+
+/// How to get the user's attention when a reminder fires, on top of the in-app banner
+#[derive(Debug, Default, Clone, Copy, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum NotificationMethod {
+    /// only show the banner
+    #[default]
+    None,
+    /// ring the terminal bell
+    Bell,
+    /// send an OSC 9 desktop notification (iTerm2, WezTerm, kitty, ...)
+    Osc9,
+    /// send an OSC 777 desktop notification (foot, urxvt, VTE based terminals, ...)
+    Osc777,
+}
+
+impl NotificationMethod {
+    /// Writes the escape sequence for this notification method straight to the terminal
+    pub fn notify(&self, message: &str) {
+        // the message can't contain control characters, or it could end the escape sequence early
+        let message: String = message.chars().filter(|c| !c.is_control()).collect();
+        let sequence = match self {
+            NotificationMethod::None => return,
+            NotificationMethod::Bell => String::from("\x07"),
+            NotificationMethod::Osc9 => format!("\x1b]9;{}\x07", message),
+            NotificationMethod::Osc777 => format!("\x1b]777;notify;talendar;{}\x07", message),
+        };
+
+        let mut stdout = std::io::stdout();
+        _ = stdout.write_all(sequence.as_bytes());
+        _ = stdout.flush();
+    }
+}
+
+/// Returns the popup reminders that should be showing at `now`. A reminder is due from its
+/// reminder time until the event ends, unless it has been dismissed.
+pub fn due_reminders(calendar_client: &CalendarClient, now: DateTime<Local>) -> Vec<Reminder> {
+    let today = now.date_naive();
+    let mut reminders = Vec::new();
+
+    for days in 0..=REMINDER_LOOKAHEAD_DAYS {
+        let Some(date) = today.checked_add_days(Days::new(days)) else {
+            break;
+        };
+        let Some(events) = calendar_client.get_events_by_date(&date) else {
+            continue;
+        };
+
+        for event in events {
+            let Some((event_start, event_end)) = event.get_local_bounds() else {
+                continue;
+            };
+            if now >= event_end {
+                continue;
+            }
+
+            for minutes in popup_reminder_minutes(calendar_client, event) {
+                let key = reminder_key(event, event_start, minutes);
+                let fire_at = event_start - TimeDelta::minutes(minutes.into());
+                if fire_at <= now && !calendar_client.is_reminder_dismissed(&key) {
+                    reminders.push(Reminder {
+                        key,
+                        summary: event.summary.clone().unwrap_or(String::from(DEFAULT_TITLE)),
+                        event_start,
+                    });
+                }
+            }
+        }
+    }
+
+    reminders
+}
+
+/// Returns how many minutes before the event each of its popup reminders fires. Events using the
+/// default reminders get the defaults of their calendar.
+fn popup_reminder_minutes(calendar_client: &CalendarClient, event: &CalendarEvent) -> Vec<i32> {
+    let Some(reminders) = &event.reminders else {
+        return Vec::new();
+    };
+
+    let reminders = if reminders.use_default == Some(true) {
+        calendar_client
+            .get_event_calendar(event)
+            .and_then(|calendar| calendar.default_reminders.as_ref())
+    } else {
+        reminders.overrides.as_ref()
+    };
+
+    reminders
+        .map(|reminders| {
+            reminders
+                .iter()
+                .filter(|reminder| reminder.method.as_deref() == Some("popup"))
+                .filter_map(|reminder| reminder.minutes)
+                .collect()
+        })
+        .unwrap_or_default()
+}
+
+/// Recurring events share an id between instances in some calendars, so the start time is part
+/// of the key too
+fn reminder_key(event: &CalendarEvent, event_start: DateTime<Local>, minutes: i32) -> String {
+    let event_id = event.id.as_deref().unwrap_or_default();
+    format!("{}@{}-{}", event_id, event_start.timestamp(), minutes)
+}
@@ -0,0 +1,202 @@
+use std::time::Duration;
+
+use chrono::{DateTime, Utc};
+use google_calendar3::common::{Delegate, Response, Retry};
+use google_calendar3::hyper::{header::RETRY_AFTER, HeaderMap, StatusCode};
+use tokio_retry::strategy::{jitter, ExponentialBackoff};
+
+/// How many times a request is retried before we give up on it
+const MAX_RETRIES: usize = 5;
+
+/// The longest we wait between tries, and the longest Retry-After we're willing to wait for
+const MAX_DELAY: Duration = Duration::from_secs(32);
+
+/// Why Google answers 403 when we've made too many requests, rather than 429
+const RATE_LIMIT_REASONS: [&str; 2] = ["rateLimitExceeded", "userRateLimitExceeded"];
+
+/// Retries requests that Google turned away because we made too many, or because of a problem
+/// on its side, backing off exponentially with jitter. A Retry-After from Google is waited for
+/// instead of the backoff.
+///
+/// Pass a new one to each request with `.delegate(&mut RetryDelegate::new())`, since it counts
+/// the tries of one request.
+pub struct RetryDelegate {
+    delays: Box<dyn Iterator<Item = Duration> + Send>,
+    retry_server_errors: bool,
+}
+
+impl RetryDelegate {
+    pub fn new() -> Self {
+        Self::with_server_errors(true)
+    }
+
+    /// Only retries rate limits, for requests that create something. A server error doesn't say
+    /// whether the event was created before it happened, and creating it again would send its
+    /// guests a second invitation.
+    pub fn for_create() -> Self {
+        Self::with_server_errors(false)
+    }
+
+    fn with_server_errors(retry_server_errors: bool) -> Self {
+        // 1s, 2s, 4s and so on, each between half and all of that so that requests that failed
+        // together don't all try again together
+        let delays = ExponentialBackoff::from_millis(2)
+            .factor(500)
+            .max_delay(MAX_DELAY)
+            .map(|delay| delay / 2 + jitter(delay / 2))
+            .take(MAX_RETRIES);
+        RetryDelegate {
+            delays: Box::new(delays),
+            retry_server_errors,
+        }
+    }
+}
+
+impl Default for RetryDelegate {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl Delegate for RetryDelegate {
+    fn http_failure(&mut self, response: &Response, err: Option<&serde_json::Value>) -> Retry {
+        let status = response.status();
+        let retryable =
+            is_rate_limited(status, err) || (self.retry_server_errors && status.is_server_error());
+        if !retryable {
+            return Retry::Abort;
+        }
+        let Some(delay) = self.delays.next() else {
+            return Retry::Abort;
+        };
+        match retry_after(response.headers(), Utc::now()) {
+            Some(retry_after) if retry_after > MAX_DELAY => Retry::Abort,
+            Some(retry_after) => Retry::After(retry_after),
+            None => Retry::After(delay),
+        }
+    }
+}
+
+/// Returns `true` when Google turned the request away because we made too many
+fn is_rate_limited(status: StatusCode, err: Option<&serde_json::Value>) -> bool {
+    match status {
+        StatusCode::TOO_MANY_REQUESTS => true,
+        StatusCode::FORBIDDEN => err
+            .and_then(|err| err["error"]["errors"].as_array())
+            .is_some_and(|errors| {
+                errors.iter().any(|error| {
+                    error["reason"]
+                        .as_str()
+                        .is_some_and(|reason| RATE_LIMIT_REASONS.contains(&reason))
+                })
+            }),
+        _ => false,
+    }
+}
+
+/// How long the Retry-After header asks us to wait, given either in seconds or as the time to
+/// try again at
+fn retry_after(headers: &HeaderMap, now: DateTime<Utc>) -> Option<Duration> {
+    let value = headers.get(RETRY_AFTER)?.to_str().ok()?.trim();
+    if let Ok(seconds) = value.parse::<u64>() {
+        return Some(Duration::from_secs(seconds));
+    }
+    let retry_at = DateTime::parse_from_rfc2822(value).ok()?;
+    // a time that's passed already means we can try again straight away
+    Some(
+        (retry_at.with_timezone(&Utc) - now)
+            .to_std()
+            .unwrap_or_default(),
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::TimeZone;
+    use google_calendar3::hyper::header::HeaderValue;
+    use serde_json::json;
+
+    use super::*;
+
+    fn headers(retry_after: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(RETRY_AFTER, HeaderValue::from_str(retry_after).unwrap());
+        headers
+    }
+
+    fn now() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2025, 1, 31, 9, 0, 0).unwrap()
+    }
+
+    /// The body Google sends with a 403, with `reason` as the reason of its only error
+    fn forbidden(reason: &str) -> serde_json::Value {
+        json!({
+            "error": {
+                "code": 403,
+                "message": "Forbidden",
+                "errors": [{ "domain": "usageLimits", "reason": reason }],
+            }
+        })
+    }
+
+    #[test]
+    fn rate_limits() {
+        assert!(is_rate_limited(StatusCode::TOO_MANY_REQUESTS, None));
+        assert!(is_rate_limited(
+            StatusCode::FORBIDDEN,
+            Some(&forbidden("rateLimitExceeded"))
+        ));
+        assert!(is_rate_limited(
+            StatusCode::FORBIDDEN,
+            Some(&forbidden("userRateLimitExceeded"))
+        ));
+    }
+
+    #[test]
+    fn other_errors_are_not_rate_limits() {
+        assert!(!is_rate_limited(StatusCode::INTERNAL_SERVER_ERROR, None));
+        assert!(!is_rate_limited(StatusCode::BAD_REQUEST, None));
+        assert!(!is_rate_limited(StatusCode::NOT_FOUND, None));
+        assert!(!is_rate_limited(StatusCode::FORBIDDEN, None));
+        assert!(!is_rate_limited(
+            StatusCode::FORBIDDEN,
+            Some(&forbidden("forbidden"))
+        ));
+        assert!(!is_rate_limited(
+            StatusCode::FORBIDDEN,
+            Some(&json!({ "error": "rateLimitExceeded" }))
+        ));
+    }
+
+    #[test]
+    fn retry_after_in_seconds() {
+        assert_eq!(
+            retry_after(&headers("30"), now()),
+            Some(Duration::from_secs(30))
+        );
+        assert_eq!(
+            retry_after(&headers(" 5 "), now()),
+            Some(Duration::from_secs(5))
+        );
+    }
+
+    #[test]
+    fn retry_after_as_a_date() {
+        assert_eq!(
+            retry_after(&headers("Fri, 31 Jan 2025 09:00:45 GMT"), now()),
+            Some(Duration::from_secs(45))
+        );
+        // a time that's passed means now
+        assert_eq!(
+            retry_after(&headers("Fri, 31 Jan 2025 08:59:00 GMT"), now()),
+            Some(Duration::ZERO)
+        );
+    }
+
+    #[test]
+    fn missing_or_unreadable_retry_after() {
+        assert_eq!(retry_after(&HeaderMap::new(), now()), None);
+        assert_eq!(retry_after(&headers("soon"), now()), None);
+        assert_eq!(retry_after(&headers("-5"), now()), None);
+    }
+}
@@ -0,0 +1,351 @@
+use chrono::{DateTime, Local, Locale, TimeDelta};
+use google_calendar3::api::Event as CalendarEvent;
+use serde::Deserialize;
+use serde_json::json;
+
+use crate::google_cal_backend::{CalendarCache, CalendarEventExt};
+use crate::utils::{duration_string, time_string};
+
+const DEFAULT_TITLE: &str = "No title";
+
+/// Templates for `talendar status`. In each template `{summary}`, `{location}`, `{start}` and
+/// `{end}` are replaced by those of the event, and `{until}` by how long until it starts or ends.
+#[derive(Debug, Deserialize)]
+#[serde(default)]
+pub struct StatusConfig {
+    /// Shown while an event is on, e.g. "Standup until 09:15"
+    pub current: String,
+    /// Shown when the next event starts within `soon_minutes`, e.g. "Standup in 12m"
+    pub next: String,
+    /// Shown when the next event today is further away, e.g. "Free until 15:00"
+    pub free: String,
+    /// Shown when there are no more events today
+    pub idle: String,
+    /// How many minutes before an event it counts as starting soon
+    pub soon_minutes: u32,
+}
+
+impl Default for StatusConfig {
+    fn default() -> Self {
+        StatusConfig {
+            current: String::from("{summary} until {end}"),
+            next: String::from("{summary} in {until}"),
+            free: String::from("Free until {start}"),
+            idle: String::from("Free"),
+            soon_minutes: 30,
+        }
+    }
+}
+
+/// What `talendar status` shows
+pub struct Status {
+    pub text: String,
+    /// the rest of today's events, one per line
+    pub tooltip: String,
+    /// "current", "next", "free" or "idle", so that bars can style each differently
+    pub class: &'static str,
+}
+
+impl Status {
+    /// Returns the status in the format of a waybar custom module. Waybar reads the text and
+    /// tooltip as Pango markup, so event titles like "Q&A" are escaped.
+    pub fn to_waybar_json(&self) -> String {
+        json!({
+            "text": escape_markup(&self.text),
+            "tooltip": escape_markup(&self.tooltip),
+            "class": self.class,
+        })
+        .to_string()
+    }
+}
+
+/// Escapes the characters that mean something in Pango markup
+fn escape_markup(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+}
+
+/// Works out the status at `now` from the events in `cache`. All day events and events we've
+/// declined don't make us busy, so they're left out.
+pub fn status(
+    cache: &CalendarCache,
+    config: &StatusConfig,
+    now: DateTime<Local>,
+    locale: Locale,
+) -> Status {
+    let end_of_today = now
+        .date_naive()
+        .succ_opt()
+        .and_then(|tomorrow| tomorrow.and_hms_opt(0, 0, 0))
+        .and_then(|midnight| midnight.and_local_timezone(Local).earliest())
+        .unwrap_or(now);
+
+    let events: Vec<(&CalendarEvent, DateTime<Local>, DateTime<Local>)> = cache
+        .events_between(now, end_of_today)
+        .into_iter()
+        .filter(|event| {
+            event
+                .start
+                .as_ref()
+                .is_some_and(|start| start.date_time.is_some())
+                && !event.is_declined()
+        })
+        .filter_map(|event| {
+            let (start, end) = event.get_local_bounds()?;
+            Some((event, start, end))
+        })
+        .collect();
+
+    let tooltip = events
+        .iter()
+        .map(|(event, start, end)| {
+            format!(
+                "{} - {} {}",
+                time_string(start, locale),
+                time_string(end, locale),
+                event.summary.as_deref().unwrap_or(DEFAULT_TITLE)
+            )
+        })
+        .collect::<Vec<String>>()
+        .join("\n");
+
+    let current = events.iter().find(|(_, start, _)| *start <= now);
+    let next = events.iter().find(|(_, start, _)| *start > now);
+
+    let (template, class, event) = match (current, next) {
+        (Some(current), _) => (&config.current, "current", Some(current)),
+        (None, Some(next)) if next.1 - now <= TimeDelta::minutes(config.soon_minutes.into()) => {
+            (&config.next, "next", Some(next))
+        }
+        (None, Some(next)) => (&config.free, "free", Some(next)),
+        (None, None) => (&config.idle, "idle", None),
+    };
+
+    let text = match event {
+        Some((event, start, end)) => fill_template(template, event, *start, *end, now, locale),
+        None => template.clone(),
+    };
+
+    Status {
+        text,
+        tooltip,
+        class,
+    }
+}
+
+/// Replaces the placeholders in `template` with the details of `event`. Anything that looks like
+/// a placeholder in the details themselves, like a title with "{end}" in it, is left as it is.
+fn fill_template(
+    template: &str,
+    event: &CalendarEvent,
+    start: DateTime<Local>,
+    end: DateTime<Local>,
+    now: DateTime<Local>,
+    locale: Locale,
+) -> String {
+    // ongoing events count down to their end, upcoming ones to their start
+    let until = if start <= now { end - now } else { start - now };
+    // round up, so that an event starting in 30 seconds isn't "in 0m"
+    let until = TimeDelta::minutes((until.num_seconds() + 59) / 60);
+
+    let mut filled = String::new();
+    let mut rest = template;
+    while let Some(open) = rest.find('{') {
+        filled.push_str(&rest[..open]);
+        rest = &rest[open..];
+        let Some(close) = rest.find('}') else {
+            break;
+        };
+        match &rest[1..close] {
+            "summary" => filled.push_str(event.summary.as_deref().unwrap_or(DEFAULT_TITLE)),
+            "location" => filled.push_str(event.location.as_deref().unwrap_or_default()),
+            "start" => filled.push_str(&time_string(&start, locale)),
+            "end" => filled.push_str(&time_string(&end, locale)),
+            "until" => filled.push_str(&duration_string(until)),
+            // not a placeholder, so it stays, though a placeholder may start inside it
+            _ => {
+                filled.push('{');
+                rest = &rest[1..];
+                continue;
+            }
+        }
+        rest = &rest[close + 1..];
+    }
+    filled.push_str(rest);
+    filled
+}
+
+#[cfg(test)]
+mod tests {
+    use chrono::{NaiveDate, TimeZone};
+    use google_calendar3::api::{EventAttendee, EventDateTime};
+
+    use super::*;
+
+    /// Uses a 24 hour clock
+    const LOCALE: Locale = Locale::en_GB;
+
+    fn at(hour: u32, minute: u32, second: u32) -> DateTime<Local> {
+        Local
+            .with_ymd_and_hms(2025, 1, 31, hour, minute, second)
+            .unwrap()
+    }
+
+    fn event(summary: &str, start: DateTime<Local>, end: DateTime<Local>) -> CalendarEvent {
+        CalendarEvent {
+            summary: Some(summary.to_string()),
+            start: Some(EventDateTime {
+                date_time: Some(start.to_utc()),
+                ..Default::default()
+            }),
+            end: Some(EventDateTime {
+                date_time: Some(end.to_utc()),
+                ..Default::default()
+            }),
+            ..Default::default()
+        }
+    }
+
+    fn cache(events: Vec<CalendarEvent>) -> CalendarCache {
+        let mut cache = CalendarCache::default();
+        for event in events {
+            let date = event.get_naive_date().unwrap();
+            cache.events.entry(date).or_default().push(event);
+        }
+        cache
+    }
+
+    fn status_at(events: Vec<CalendarEvent>, now: DateTime<Local>) -> Status {
+        status(&cache(events), &StatusConfig::default(), now, LOCALE)
+    }
+
+    #[test]
+    fn idle_without_events() {
+        let status = status_at(Vec::new(), at(9, 0, 0));
+        assert_eq!(status.text, "Free");
+        assert_eq!(status.class, "idle");
+        assert_eq!(status.tooltip, "");
+    }
+
+    #[test]
+    fn current_event_counts_down_to_its_end() {
+        let events = vec![event("Standup", at(9, 0, 0), at(9, 30, 0))];
+        let status = status_at(events, at(9, 10, 0));
+        assert_eq!(status.text, "Standup until 09:30");
+        assert_eq!(status.class, "current");
+    }
+
+    #[test]
+    fn next_event_counts_down_to_its_start() {
+        let events = vec![event("Standup", at(9, 30, 0), at(9, 45, 0))];
+        let status = status_at(events.clone(), at(9, 18, 0));
+        assert_eq!(status.text, "Standup in 12m");
+        assert_eq!(status.class, "next");
+
+        // rounded up, so it's never "in 0m"
+        let status = status_at(events, at(9, 29, 30));
+        assert_eq!(status.text, "Standup in 1m");
+    }
+
+    #[test]
+    fn free_until_events_that_arent_soon() {
+        let events = vec![event("Review", at(15, 0, 0), at(16, 0, 0))];
+        let status = status_at(events, at(9, 0, 0));
+        assert_eq!(status.text, "Free until 15:00");
+        assert_eq!(status.class, "free");
+    }
+
+    #[test]
+    fn fills_in_every_placeholder() {
+        let mut review = event("Review", at(13, 0, 0), at(14, 30, 0));
+        review.location = Some(String::from("Room 2"));
+        let config = StatusConfig {
+            free: String::from("{summary} at {location}, {start}-{end}, in {until}"),
+            ..Default::default()
+        };
+        let status = status(&cache(vec![review]), &config, at(9, 45, 0), LOCALE);
+        assert_eq!(status.text, "Review at Room 2, 13:00-14:30, in 3h 15m");
+    }
+
+    #[test]
+    fn placeholders_in_event_details_are_left_alone() {
+        let mut review = event("Review {end}", at(13, 0, 0), at(14, 30, 0));
+        review.location = Some(String::from("{summary}"));
+        let config = StatusConfig {
+            free: String::from("{summary} at {location} {{start}} {unknown} {"),
+            ..Default::default()
+        };
+        let status = status(&cache(vec![review]), &config, at(9, 45, 0), LOCALE);
+        assert_eq!(status.text, "Review {end} at {summary} {13:00} {unknown} {");
+    }
+
+    #[test]
+    fn all_day_declined_and_later_events_are_left_out() {
+        let holiday = CalendarEvent {
+            summary: Some(String::from("Holiday")),
+            start: Some(EventDateTime {
+                date: NaiveDate::from_ymd_opt(2025, 1, 31),
+                ..Default::default()
+            }),
+            end: Some(EventDateTime {
+                date: NaiveDate::from_ymd_opt(2025, 2, 1),
+                ..Default::default()
+            }),
+            ..Default::default()
+        };
+        let mut declined = event("Declined", at(9, 0, 0), at(10, 0, 0));
+        declined.attendees = Some(vec![EventAttendee {
+            self_: Some(true),
+            response_status: Some(String::from("declined")),
+            ..Default::default()
+        }]);
+        let tomorrow = event(
+            "Tomorrow",
+            at(9, 0, 0) + TimeDelta::days(1),
+            at(10, 0, 0) + TimeDelta::days(1),
+        );
+        let earlier = event("Earlier", at(7, 0, 0), at(8, 0, 0));
+
+        let status = status_at(vec![holiday, declined, tomorrow, earlier], at(9, 15, 0));
+        assert_eq!(status.text, "Free");
+        assert_eq!(status.class, "idle");
+    }
+
+    #[test]
+    fn tooltip_lists_the_rest_of_today() {
+        let events = vec![
+            event("Standup", at(9, 0, 0), at(9, 15, 0)),
+            event("Lunch", at(12, 0, 0), at(13, 0, 0)),
+            event("Review", at(15, 0, 0), at(16, 0, 0)),
+        ];
+        let status = status_at(events, at(11, 0, 0));
+        assert_eq!(status.tooltip, "12:00 - 13:00 Lunch\n15:00 - 16:00 Review");
+    }
+
+    #[test]
+    fn waybar_json() {
+        let status = Status {
+            text: String::from("Standup in 5m"),
+            tooltip: String::from("09:00 - 09:15 Standup"),
+            class: "next",
+        };
+        let json: serde_json::Value = serde_json::from_str(&status.to_waybar_json()).unwrap();
+        assert_eq!(
+            json,
+            json!({ "text": "Standup in 5m", "tooltip": "09:00 - 09:15 Standup", "class": "next" })
+        );
+    }
+
+    #[test]
+    fn waybar_json_escapes_markup() {
+        let status = Status {
+            text: String::from("Q&A <team> in 5m"),
+            tooltip: String::from("09:00 - 09:15 Q&A <team>"),
+            class: "next",
+        };
+        let json: serde_json::Value = serde_json::from_str(&status.to_waybar_json()).unwrap();
+        assert_eq!(json["text"], "Q&amp;A &lt;team&gt; in 5m");
+        assert_eq!(json["tooltip"], "09:00 - 09:15 Q&amp;A &lt;team&gt;");
+    }
+}
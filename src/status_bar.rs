@@ -0,0 +1,140 @@
+use std::time::Instant;
+
+use chrono::{DateTime, Local, Locale};
+use ratatui::{
+    buffer::Buffer,
+    layout::{Constraint, Layout, Rect},
+    text::{Line, Span},
+    widgets::Widget,
+};
+
+use crate::google_cal_backend::SyncProgress;
+use crate::theme::Theme;
+use crate::utils::{date_string, time_string, truncate_line, SPINNER_FRAMES};
+
+/// How often the spinner moves on to its next frame
+const SPINNER_FRAME_MILLIS: u128 = 100;
+
+/// The bar along the bottom of the screen: how up to date the calendar is on the left, and what's
+/// being shown on the right
+pub struct StatusBar<'a> {
+    /// the name of the view, e.g. "Month"
+    view: &'a str,
+    timezone: &'a str,
+    account: Option<&'a str>,
+    last_synced: Option<DateTime<Local>>,
+    /// when the sync that's running started, if one is
+    syncing_since: Option<Instant>,
+    sync_progress: Option<SyncProgress>,
+    offline: bool,
+    /// the number of changes waiting to be sent to Google
+    offline_edits: usize,
+    theme: &'a Theme,
+    locale: Locale,
+}
+
+impl<'a> StatusBar<'a> {
+    pub fn new(view: &'a str, timezone: &'a str, theme: &'a Theme, locale: Locale) -> Self {
+        StatusBar {
+            view,
+            timezone,
+            account: None,
+            last_synced: None,
+            syncing_since: None,
+            sync_progress: None,
+            offline: false,
+            offline_edits: 0,
+            theme,
+            locale,
+        }
+    }
+
+    /// Shows when the calendars of `account` were last synced
+    pub fn last_synced(
+        mut self,
+        account: Option<&'a str>,
+        last_synced: Option<DateTime<Local>>,
+    ) -> Self {
+        self.account = account;
+        self.last_synced = last_synced;
+        self
+    }
+
+    /// Shows a spinner and how many calendars have synced instead of the last sync, if a sync
+    /// started at `syncing_since` is running
+    pub fn syncing(
+        mut self,
+        syncing_since: Option<Instant>,
+        sync_progress: Option<SyncProgress>,
+    ) -> Self {
+        self.syncing_since = syncing_since;
+        self.sync_progress = sync_progress;
+        self
+    }
+
+    /// Shows that Google can't be reached, and how many changes are waiting until it can be
+    pub fn offline(mut self, offline: bool, offline_edits: usize) -> Self {
+        self.offline = offline;
+        self.offline_edits = offline_edits;
+        self
+    }
+
+    fn sync_status(&self) -> String {
+        if let Some(syncing_since) = self.syncing_since {
+            let frame = syncing_since.elapsed().as_millis() / SPINNER_FRAME_MILLIS;
+            let spinner = SPINNER_FRAMES[frame as usize % SPINNER_FRAMES.len()];
+            return match self.sync_progress {
+                Some(SyncProgress { synced, total }) if total > 0 => {
+                    format!("{spinner} Syncing {synced}/{total} calendars")
+                }
+                _ => format!("{spinner} Syncing"),
+            };
+        }
+
+        let Some(last_synced) = self.last_synced else {
+            return String::from("Not synced yet");
+        };
+        // the date only matters if it wasn't today
+        let mut when = time_string(&last_synced, self.locale);
+        if last_synced.date_naive() != Local::now().date_naive() {
+            when = format!(
+                "{} {when}",
+                date_string(last_synced.date_naive(), self.locale)
+            );
+        }
+        match self.account {
+            Some(account) => format!("{account} synced {when}"),
+            None => format!("Synced {when}"),
+        }
+    }
+}
+
+impl Widget for StatusBar<'_> {
+    fn render(self, area: Rect, buf: &mut Buffer) {
+        buf.set_style(area, self.theme.status_bar);
+
+        let mut spans = vec![Span::from(format!(" {}", self.sync_status()))];
+        if self.offline {
+            spans.push(Span::from(" · offline - showing cached data"));
+        }
+        match self.offline_edits {
+            0 => {}
+            1 => spans.push(Span::from(" · 1 edit waiting")),
+            edits => spans.push(Span::from(format!(" · {edits} edits waiting"))),
+        }
+        let left = Line::from(spans);
+
+        // the sync status matters more, so the view gives way to it on narrow screens
+        let right = Line::from(format!("{} · {} ", self.view, self.timezone)).right_aligned();
+        let right_width = if left.width() + right.width() < area.width as usize {
+            right.width() as u16
+        } else {
+            0
+        };
+        let [left_area, right_area] =
+            Layout::horizontal([Constraint::Fill(1), Constraint::Length(right_width)]).areas(area);
+
+        truncate_line(left, left_area.width as usize).render(left_area, buf);
+        right.render(right_area, buf);
+    }
+}
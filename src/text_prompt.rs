@@ -0,0 +1,77 @@
+use crossterm::event::{KeyCode, KeyEvent};
+use ratatui::{
+    layout::{Constraint, Flex, Layout},
+    widgets::{Clear, Paragraph},
+    Frame,
+};
+
+use crate::theme::Theme;
+
+/// A single line of text input, shown in a small popup
+pub struct TextPrompt {
+    title: String,
+    input: String,
+}
+
+/// What happened to a [`TextPrompt`] after it handled a key event
+pub enum PromptEvent {
+    /// The user is still typing
+    Editing,
+    /// The user pressed enter, submitting the input
+    Submitted(String),
+    /// The user pressed escape
+    Cancelled,
+}
+
+impl TextPrompt {
+    pub fn new(title: impl Into<String>) -> Self {
+        TextPrompt {
+            title: title.into(),
+            input: String::new(),
+        }
+    }
+
+    /// A prompt that starts out with `input` already typed in
+    pub fn with_input(title: impl Into<String>, input: impl Into<String>) -> Self {
+        TextPrompt {
+            title: title.into(),
+            input: input.into(),
+        }
+    }
+
+    pub fn on_key_event(&mut self, key: KeyEvent) -> PromptEvent {
+        match key.code {
+            KeyCode::Enter => PromptEvent::Submitted(std::mem::take(&mut self.input)),
+            KeyCode::Esc => PromptEvent::Cancelled,
+            KeyCode::Backspace => {
+                self.input.pop();
+                PromptEvent::Editing
+            }
+            KeyCode::Char(c) => {
+                self.input.push(c);
+                PromptEvent::Editing
+            }
+            _ => PromptEvent::Editing,
+        }
+    }
+}
+
+/// Draws `prompt` as a three line high popup in the middle of the screen
+pub fn draw_text_prompt(frame: &mut Frame, prompt: &TextPrompt, theme: &Theme) {
+    let vertical = Layout::vertical([Constraint::Length(3)]).flex(Flex::Center);
+    let horizontal = Layout::horizontal([Constraint::Percentage(60)]).flex(Flex::Center);
+    let [area] = vertical.areas(frame.area());
+    let [area] = horizontal.areas(area);
+    frame.render_widget(Clear, area); // this clears out the background
+
+    let block = theme
+        .popup_block()
+        .title(prompt.title.as_str())
+        .title_bottom("[Enter] submit  [Esc] cancel");
+    let paragraph = Paragraph::new(prompt.input.as_str()).block(block);
+    frame.render_widget(paragraph, area);
+
+    // put the cursor at the end of the input
+    let cursor_x = area.x + 1 + prompt.input.chars().count() as u16;
+    frame.set_cursor_position((cursor_x.min(area.right().saturating_sub(2)), area.y + 1));
+}
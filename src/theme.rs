@@ -0,0 +1,205 @@
+use ratatui::{
+    style::{Color, Modifier, Style},
+    widgets::{Block, BorderType},
+};
+use serde::Deserialize;
+
+use crate::colors::EventColor;
+
+/// The built in themes, chosen with `theme` in the config
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum ThemeName {
+    /// for terminals with a dark background
+    #[default]
+    Dark,
+    /// for terminals with a light background
+    Light,
+    /// bold, bright colors and heavy borders, for anyone who finds the others hard to read
+    HighContrast,
+    /// no colors at all, only bold, dim, underlined and reversed text. Used whenever `NO_COLOR`
+    /// is set.
+    NoColor,
+}
+
+/// The styles of everything drawn in the calendar apart from the events' own colors
+#[derive(Debug, Clone)]
+pub struct Theme {
+    /// the borders of the days
+    pub border: Style,
+    /// the border of the selected day
+    pub selected_border: Style,
+    pub selected_border_type: BorderType,
+    /// the selected row of lists and tables, like the selected event
+    pub selection: Style,
+    /// the number of today's date
+    pub today: Style,
+    /// the names and numbers of Saturdays and Sundays
+    pub weekend: Style,
+    /// days from the months before and after the one shown
+    pub adjacent_month: Style,
+    /// the month and year at the top of the screen
+    pub header: Style,
+    /// the borders and titles of popups
+    pub popup: Style,
+    /// messages and status along the bottom of the screen
+    pub status_bar: Style,
+    /// errors along the bottom of the screen
+    pub error: Style,
+    /// things that are less important than what's around them, like week numbers and times
+    pub secondary: Style,
+    /// events that have already ended
+    pub past_event: Style,
+    /// all day events are drawn as a bar in this style, behind the event's color
+    pub all_day_event: Style,
+    /// the color of events without a color of their own or from their calendar
+    pub default_event: EventColor,
+    /// whether events are drawn in their own colors at all
+    pub event_colors: bool,
+}
+
+impl Theme {
+    /// Returns the theme called `name`, or the theme without colors if `NO_COLOR` is set
+    /// (see <https://no-color.org>)
+    pub fn load(name: ThemeName) -> Self {
+        let no_color = std::env::var("NO_COLOR").is_ok_and(|value| !value.is_empty());
+        let name = if no_color { ThemeName::NoColor } else { name };
+
+        match name {
+            ThemeName::Dark => Theme::dark(),
+            ThemeName::Light => Theme::light(),
+            ThemeName::HighContrast => Theme::high_contrast(),
+            ThemeName::NoColor => Theme::no_color(),
+        }
+    }
+
+    fn dark() -> Self {
+        Theme {
+            border: Style::default(),
+            selected_border: Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::BOLD),
+            selected_border_type: BorderType::Thick,
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            today: Style::default()
+                .fg(Color::LightBlue)
+                .add_modifier(Modifier::BOLD),
+            weekend: Style::default().fg(Color::Gray),
+            adjacent_month: Style::default().add_modifier(Modifier::DIM),
+            header: Style::default().add_modifier(Modifier::BOLD),
+            popup: Style::default().fg(Color::LightBlue),
+            status_bar: Style::default().add_modifier(Modifier::REVERSED),
+            error: Style::default().bg(Color::Red).fg(Color::White),
+            secondary: Style::default().add_modifier(Modifier::DIM),
+            past_event: Style::default().fg(Color::DarkGray),
+            all_day_event: Style::default(),
+            default_event: EventColor {
+                color: Color::Gray,
+                text: Color::Black,
+            },
+            event_colors: true,
+        }
+    }
+
+    fn light() -> Self {
+        Theme {
+            border: Style::default().fg(Color::DarkGray),
+            selected_border: Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+            selected_border_type: BorderType::Thick,
+            selection: Style::default().bg(Color::Blue).fg(Color::White),
+            today: Style::default()
+                .fg(Color::Blue)
+                .add_modifier(Modifier::BOLD),
+            weekend: Style::default().fg(Color::DarkGray),
+            adjacent_month: Style::default().fg(Color::Gray),
+            header: Style::default().add_modifier(Modifier::BOLD),
+            popup: Style::default().fg(Color::Blue),
+            status_bar: Style::default().bg(Color::Gray).fg(Color::Black),
+            error: Style::default().bg(Color::Red).fg(Color::White),
+            secondary: Style::default().fg(Color::DarkGray),
+            past_event: Style::default().fg(Color::Gray),
+            all_day_event: Style::default(),
+            default_event: EventColor {
+                color: Color::DarkGray,
+                text: Color::White,
+            },
+            event_colors: true,
+        }
+    }
+
+    fn high_contrast() -> Self {
+        Theme {
+            border: Style::default().fg(Color::White),
+            selected_border: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            selected_border_type: BorderType::Double,
+            selection: Style::default()
+                .bg(Color::Yellow)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            today: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            weekend: Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            adjacent_month: Style::default().fg(Color::Gray),
+            header: Style::default()
+                .fg(Color::White)
+                .add_modifier(Modifier::BOLD),
+            popup: Style::default()
+                .fg(Color::Yellow)
+                .add_modifier(Modifier::BOLD),
+            status_bar: Style::default()
+                .bg(Color::White)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            error: Style::default()
+                .bg(Color::LightRed)
+                .fg(Color::Black)
+                .add_modifier(Modifier::BOLD),
+            secondary: Style::default().fg(Color::White),
+            past_event: Style::default().fg(Color::Gray),
+            all_day_event: Style::default().add_modifier(Modifier::BOLD),
+            default_event: EventColor {
+                color: Color::White,
+                text: Color::Black,
+            },
+            event_colors: true,
+        }
+    }
+
+    fn no_color() -> Self {
+        Theme {
+            border: Style::default(),
+            selected_border: Style::default().add_modifier(Modifier::BOLD),
+            selected_border_type: BorderType::Double,
+            selection: Style::default().add_modifier(Modifier::REVERSED),
+            today: Style::default().add_modifier(Modifier::BOLD | Modifier::UNDERLINED),
+            weekend: Style::default(),
+            adjacent_month: Style::default().add_modifier(Modifier::DIM),
+            header: Style::default().add_modifier(Modifier::BOLD),
+            popup: Style::default(),
+            status_bar: Style::default().add_modifier(Modifier::REVERSED),
+            error: Style::default().add_modifier(Modifier::REVERSED | Modifier::BOLD),
+            secondary: Style::default().add_modifier(Modifier::DIM),
+            past_event: Style::default().add_modifier(Modifier::DIM),
+            all_day_event: Style::default().add_modifier(Modifier::REVERSED),
+            default_event: EventColor {
+                color: Color::Reset,
+                text: Color::Reset,
+            },
+            event_colors: false,
+        }
+    }
+
+    /// Returns a bordered block in the style of a popup
+    pub fn popup_block<'a>(&self) -> Block<'a> {
+        Block::bordered()
+            .border_style(self.popup)
+            .title_style(self.popup)
+    }
+}
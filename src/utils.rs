@@ -1,53 +1,234 @@
-use google_calendar3::api::{Colors, Event as CalendarEvent};
-use ratatui::style;
-use std::str::FromStr;
-
-
-const DEFAULT_COLOR: &str  = "#FFFFFF";
-
-pub fn month_to_str(month: u32) -> &'static str {
-    match month {
-        1 => "January",
-        2 => "February",
-        3 => "March",
-        4 => "April",
-        5 => "May",
-        6 => "June",
-        7 => "July",
-        8 => "August",
-        9 => "September",
-        10 => "October",
-        11 => "November",
-        12 => "December",
-        _ => {
-            eprintln!("Month must be in [1, 12] but it was {}", month);
-            panic!();
-        }
-    }
+use chrono::{DateTime, Datelike, Days, Locale, Months, NaiveDate, TimeDelta, TimeZone, Weekday};
+use ratatui::{
+    layout::{Constraint, Flex, Layout, Rect},
+    text::{Line, Span},
+};
+use std::fmt::Display;
+use unicode_width::UnicodeWidthChar;
+
+/// The frames of the spinner shown while waiting on Google
+pub const SPINNER_FRAMES: [&str; 10] = ["⠋", "⠙", "⠹", "⠸", "⠼", "⠴", "⠦", "⠧", "⠇", "⠏"];
+
+/// Returns the month and year of `date`, e.g. "January 2025" or "Januar 2025"
+pub fn month_year_string(date: NaiveDate, locale: Locale) -> String {
+    date.format_localized("%B %Y", locale).to_string()
+}
+
+/// Returns the abbreviated name of `weekday`, e.g. "Mon" or "Mo"
+pub fn weekday_short_name(weekday: Weekday, locale: Locale) -> String {
+    // any date with the right weekday will do
+    let date = NaiveDate::from_isoywd_opt(2025, 1, weekday).unwrap_or_default();
+    date.format_localized("%a", locale).to_string()
+}
+
+/// Returns `true` for Saturday and Sunday
+pub fn is_weekend(weekday: Weekday) -> bool {
+    matches!(weekday, Weekday::Sat | Weekday::Sun)
+}
+
+/// Returns the full name of `weekday`, e.g. "Monday" or "Montag"
+pub fn weekday_name(weekday: Weekday, locale: Locale) -> String {
+    let date = NaiveDate::from_isoywd_opt(2025, 1, weekday).unwrap_or_default();
+    date.format_localized("%A", locale).to_string()
+}
+
+/// Returns the name of the `month`th month of the year, counting from 1, e.g. "January"
+pub fn month_name(month: u32, locale: Locale) -> String {
+    let date = NaiveDate::from_ymd_opt(2025, month, 1).unwrap_or_default();
+    date.format_localized("%B", locale).to_string()
+}
+
+/// Returns `date` in the locale's numeric date order, e.g. "01/31/2025" or "31.01.2025"
+pub fn date_string(date: NaiveDate, locale: Locale) -> String {
+    date.format_localized("%x", locale).to_string()
+}
+
+/// Returns the time of day of `date_time`, using a 12 hour clock if the locale does
+pub fn time_string<Tz: TimeZone>(date_time: &DateTime<Tz>, locale: Locale) -> String
+where
+    Tz::Offset: Display,
+{
+    date_time
+        .format_localized(time_format(locale), locale)
+        .to_string()
 }
 
-pub fn get_event_color<'a>(event: &CalendarEvent, colors: &'a Colors) -> style::Color {
-    let color_id = if let Some(color_id) = &event.color_id {
-        color_id
+/// Returns the strftime format for hours and minutes in `locale`. The locale's own time format
+/// includes seconds, so we only use it to work out whether the locale uses a 12 or 24 hour clock.
+fn time_format(locale: Locale) -> &'static str {
+    if uses_24_hour_clock(locale) {
+        "%H:%M"
     } else {
-        return style::Color::from_str(DEFAULT_COLOR).unwrap()
-    };
+        "%I:%M %p"
+    }
+}
 
-    let color = colors.event
-        // event is Option<Hashmap>, we want to lookup in &HashMap
-        .as_ref().and_then(|event| event.get(color_id.as_str()))
-        // get() returns Option<&ColorDefinition>, we want &foreground
-        .and_then(|color_definition| color_definition.foreground.as_ref())
-        // foreground is Option<&String>, convert to Option<&str>
-        .map(|foreground| foreground.as_str())
-        // now we have an Option<&str>, we can unwrap it
-        .unwrap_or(DEFAULT_COLOR);
-        // now we have a bona fide &str
+/// Returns `true` if times in `locale` are written with a 24 hour clock, e.g. 13:00 not 1:00 PM
+pub fn uses_24_hour_clock(locale: Locale) -> bool {
+    let one_pm = NaiveDate::default()
+        .and_hms_opt(13, 0, 0)
+        .unwrap_or_default()
+        .and_utc();
+    one_pm
+        .format_localized("%X", locale)
+        .to_string()
+        .contains("13")
+}
 
-    let style_color = style::Color::from_str(color);
-    match style_color {
-        Ok(res) => res,
-        _ => style::Color::from_str(DEFAULT_COLOR).unwrap()
+/// Formats a duration in hours and minutes, e.g. "1h 30m"
+pub fn duration_string(duration: TimeDelta) -> String {
+    let hours = duration.num_hours();
+    let minutes = duration.num_minutes() % 60;
+    match (hours, minutes) {
+        (0, minutes) => format!("{minutes}m"),
+        (hours, 0) => format!("{hours}h"),
+        (hours, minutes) => format!("{hours}h {minutes}m"),
+    }
+}
+
+/// Shortens `line` to at most `width` columns, ending it with an ellipsis if anything was cut off
+pub fn truncate_line(line: Line<'_>, width: usize) -> Line<'_> {
+    if line.width() <= width {
+        return line;
+    }
+
+    // leave room for the ellipsis
+    let mut remaining = width.saturating_sub(1);
+    let mut spans = Vec::new();
+    let mut ellipsis_style = line.style;
+    for span in line.spans {
+        if remaining == 0 {
+            break;
+        }
+        ellipsis_style = span.style;
+        if span.width() <= remaining {
+            remaining -= span.width();
+            spans.push(span);
+            continue;
+        }
+        let mut content = String::new();
+        for c in span.content.chars() {
+            let char_width = c.width().unwrap_or_default();
+            if char_width > remaining {
+                break;
+            }
+            remaining -= char_width;
+            content.push(c);
+        }
+        spans.push(Span::styled(content, span.style));
+        break;
+    }
+    if width > 0 {
+        spans.push(Span::styled("…", ellipsis_style));
     }
+
+    let mut truncated = Line::from(spans).style(line.style);
+    truncated.alignment = line.alignment;
+    truncated
+}
+
+/// Returns the first day of the week containing `date`, where weeks start on `week_start`
+pub fn start_of_week(date: NaiveDate, week_start: Weekday) -> NaiveDate {
+    date - Days::new(date.weekday().days_since(week_start).into())
+}
+
+/// Returns the seven weekdays in display order, starting from `week_start`
+pub fn ordered_weekdays(week_start: Weekday) -> [Weekday; 7] {
+    let mut weekday = week_start;
+    [(); 7].map(|_| {
+        let current = weekday;
+        weekday = weekday.succ();
+        current
+    })
+}
+
+/// Returns the number of week rows needed to display every day in the month containing `date`
+pub fn weeks_in_month(date: NaiveDate, week_start: Weekday) -> u32 {
+    let first_of_month = date.with_day(1).unwrap_or(date);
+    let days_in_month = ((first_of_month + Months::new(1)) - first_of_month).num_days() as u32;
+    let leading_days = first_of_month.weekday().days_since(week_start);
+    (leading_days + days_in_month).div_ceil(7)
+}
+
+/// Returns the ISO week number of the week starting on `week_start_date`.
+/// ISO weeks are defined by their Thursday, so weeks that don't start on a Monday are numbered
+/// by the Thursday that falls within them.
+pub fn iso_week_number(week_start_date: NaiveDate) -> u32 {
+    let thursday =
+        week_start_date + Days::new(Weekday::Thu.days_since(week_start_date.weekday()).into());
+    thursday.iso_week().week()
+}
+
+/// helper function to create a centered rect using up certain percentage of the available rect `r`
+pub fn popup_area(area: Rect, percent_x: u16, percent_y: u16) -> Rect {
+    let vertical = Layout::vertical([Constraint::Percentage(percent_y)]).flex(Flex::Center);
+    let horizontal = Layout::horizontal([Constraint::Percentage(percent_x)]).flex(Flex::Center);
+    let [area] = vertical.areas(area);
+    let [area] = horizontal.areas(area);
+    area
 }
 
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn date(year: i32, month: u32, day: u32) -> NaiveDate {
+        NaiveDate::from_ymd_opt(year, month, day).unwrap()
+    }
+
+    #[test]
+    fn iso_week_number_of_weeks_starting_on_monday() {
+        assert_eq!(iso_week_number(date(2025, 1, 27)), 5);
+        // the week with the year's first Thursday is week 1, even if it starts the year before
+        assert_eq!(iso_week_number(date(2024, 12, 30)), 1);
+        assert_eq!(iso_week_number(date(2020, 12, 28)), 53);
+        assert_eq!(iso_week_number(date(2021, 1, 4)), 1);
+    }
+
+    #[test]
+    fn iso_week_number_of_weeks_starting_on_other_days() {
+        // numbered by the Thursday in them, which for a Sunday start is the one after
+        assert_eq!(iso_week_number(date(2024, 12, 29)), 1);
+        assert_eq!(iso_week_number(date(2025, 1, 26)), 5);
+        assert_eq!(iso_week_number(date(2021, 1, 3)), 1);
+        // and for a Saturday start the one before the next Saturday
+        assert_eq!(iso_week_number(date(2024, 12, 28)), 1);
+        assert_eq!(iso_week_number(date(2025, 1, 25)), 5);
+        assert_eq!(iso_week_number(date(2020, 12, 26)), 53);
+        assert_eq!(iso_week_number(date(2021, 1, 2)), 1);
+    }
+
+    #[test]
+    fn weeks_in_month_with_each_week_start() {
+        // February 2021 starts on a Monday and has 28 days
+        assert_eq!(weeks_in_month(date(2021, 2, 1), Weekday::Mon), 4);
+        assert_eq!(weeks_in_month(date(2021, 2, 1), Weekday::Sun), 5);
+        assert_eq!(weeks_in_month(date(2021, 2, 1), Weekday::Sat), 5);
+        // February 2015 starts on a Sunday
+        assert_eq!(weeks_in_month(date(2015, 2, 14), Weekday::Mon), 5);
+        assert_eq!(weeks_in_month(date(2015, 2, 14), Weekday::Sun), 4);
+        assert_eq!(weeks_in_month(date(2015, 2, 14), Weekday::Sat), 5);
+        // August 2020 starts on a Saturday and has 31 days
+        assert_eq!(weeks_in_month(date(2020, 8, 31), Weekday::Mon), 6);
+        assert_eq!(weeks_in_month(date(2020, 8, 31), Weekday::Sun), 6);
+        assert_eq!(weeks_in_month(date(2020, 8, 31), Weekday::Sat), 5);
+    }
+
+    #[test]
+    fn start_of_week_with_each_week_start() {
+        // a Wednesday
+        let day = date(2025, 1, 29);
+        assert_eq!(start_of_week(day, Weekday::Mon), date(2025, 1, 27));
+        assert_eq!(start_of_week(day, Weekday::Sun), date(2025, 1, 26));
+        assert_eq!(start_of_week(day, Weekday::Sat), date(2025, 1, 25));
+        assert_eq!(start_of_week(day, Weekday::Wed), day);
+    }
+
+    #[test]
+    fn ordered_weekdays_start_on_the_week_start() {
+        use Weekday::*;
+        assert_eq!(ordered_weekdays(Mon), [Mon, Tue, Wed, Thu, Fri, Sat, Sun]);
+        assert_eq!(ordered_weekdays(Sun), [Sun, Mon, Tue, Wed, Thu, Fri, Sat]);
+        assert_eq!(ordered_weekdays(Sat), [Sat, Sun, Mon, Tue, Wed, Thu, Fri]);
+    }
+}